@@ -0,0 +1,99 @@
+//! Compares deserializing through `Compatible` against deserializing `Current` directly  
+//! Run with `cargo bench --bench deserialize`  
+//! `Alt` buffers the input once and replays it for each shape, the gap to `direct current` is
+//! the cost of that buffer  
+//! The `baseline untagged` cases run the same inputs through a plain `#[serde(untagged)]` enum,
+//! which is what `Alt` used to derive  
+use compatible_with::Compatible;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use serde::*;
+use std::hint::black_box;
+
+// Without this the current records would also match `Old` and be downgraded on the way in
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Old {
+    pub id: i64,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Current {
+    pub id: i64,
+    pub name: String,
+    pub tags: Vec<String>,
+}
+
+impl From<Old> for Current {
+    fn from(old: Old) -> Self {
+        Current {
+            id: old.id,
+            name: old.name,
+            tags: vec![],
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AssumeCurrent(
+    #[serde(deserialize_with = "Compatible::<Old, Current>::deserialize_assume_current")]
+    pub  Current,
+);
+
+// What `Alt` derived before it got its own buffer, kept so every run compares against it
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Baseline {
+    Old(Old),
+    Current(Current),
+}
+
+const RECORDS: i64 = 1_000;
+
+fn deserialize(c: &mut Criterion) {
+    let old: Vec<Old> = (0..RECORDS)
+        .map(|id| Old {
+            id,
+            name: format!("record-{id}"),
+        })
+        .collect();
+    let current: Vec<Current> = old
+        .iter()
+        .map(|old| Current {
+            id: old.id,
+            name: old.name.clone(),
+            tags: vec!["a".into(), "b".into()],
+        })
+        .collect();
+    let old_json = serde_json::to_string(&old).unwrap();
+    let current_json = serde_json::to_string(&current).unwrap();
+
+    let mut group = c.benchmark_group("deserialize");
+    group.throughput(Throughput::Elements(RECORDS as u64));
+    group.bench_function("direct current", |b| {
+        b.iter(|| serde_json::from_str::<Vec<Current>>(black_box(&current_json)).unwrap())
+    });
+    group.bench_function("compatible current", |b| {
+        b.iter(|| {
+            serde_json::from_str::<Vec<Compatible<Old, Current>>>(black_box(&current_json)).unwrap()
+        })
+    });
+    group.bench_function("compatible assume current", |b| {
+        b.iter(|| serde_json::from_str::<Vec<AssumeCurrent>>(black_box(&current_json)).unwrap())
+    });
+    group.bench_function("compatible old", |b| {
+        b.iter(|| {
+            serde_json::from_str::<Vec<Compatible<Old, Current>>>(black_box(&old_json)).unwrap()
+        })
+    });
+    group.bench_function("baseline untagged current", |b| {
+        b.iter(|| serde_json::from_str::<Vec<Baseline>>(black_box(&current_json)).unwrap())
+    });
+    group.bench_function("baseline untagged old", |b| {
+        b.iter(|| serde_json::from_str::<Vec<Baseline>>(black_box(&old_json)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, deserialize);
+criterion_main!(benches);
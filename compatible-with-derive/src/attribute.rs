@@ -0,0 +1,105 @@
+use crate::fields::{renamed, serde_attrs};
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+
+pub fn expand(
+    args: proc_macro2::TokenStream,
+    input: syn::DeriveInput,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let mut old = None;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("old") {
+            let value: syn::LitStr = meta.value()?.parse()?;
+            old = Some(value.parse::<syn::Type>()?);
+            Ok(())
+        } else {
+            Err(meta.error("expected `old = \"...\"`"))
+        }
+    });
+    syn::parse::Parser::parse2(parser, args)?;
+    let name = &input.ident;
+    let Some(old) = old else {
+        return Err(syn::Error::new(
+            name.span(),
+            "compatible_with needs `#[compatible_with(old = \"...\")]`",
+        ));
+    };
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            input.generics.span(),
+            "compatible_with does not support generic structs",
+        ));
+    }
+    let fields = match input.data {
+        syn::Data::Struct(ref data) => &data.fields,
+        _ => {
+            return Err(syn::Error::new(
+                name.span(),
+                "compatible_with can only be used on structs",
+            ))
+        }
+    };
+
+    let crate_name = syn::Ident::new("compatible_with", proc_macro2::Span::call_site());
+    let serde_crate = format!("{crate_name}::__serde");
+    let shadow = format_ident!("__{}CompatibleWith", name);
+    let container_attrs = serde_attrs(&input.attrs).collect::<Vec<_>>();
+    let rename = (!renamed(&container_attrs)).then(|| {
+        let rename = name.to_string();
+        quote! { #[serde(rename = #rename)] }
+    });
+
+    let mut shadow_fields = Vec::new();
+    let mut moves = Vec::new();
+    for (index, field) in fields.iter().enumerate() {
+        let member = match field.ident {
+            Some(ref ident) => syn::Member::Named(ident.clone()),
+            None => syn::Member::Unnamed(index.into()),
+        };
+        let attrs = serde_attrs(&field.attrs);
+        let ident = field.ident.as_ref().map(|ident| quote! { #ident: });
+        let ty = &field.ty;
+        shadow_fields.push(quote! { #(#attrs)* #ident #ty });
+        moves.push(quote! { #member: current.#member });
+    }
+    let body = match fields {
+        syn::Fields::Named(_) => quote! { { #(#shadow_fields,)* } },
+        syn::Fields::Unnamed(_) => quote! { ( #(#shadow_fields,)* ); },
+        syn::Fields::Unit => quote! { ; },
+    };
+
+    Ok(quote! {
+        #input
+
+        const _: () = {
+            #[derive(#crate_name::__serde::Deserialize)]
+            #[serde(crate = #serde_crate)]
+            #rename
+            #(#container_attrs)*
+            struct #shadow #body
+
+            impl<'de> #crate_name::__serde::Deserialize<'de> for #name {
+                fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                where
+                    D: #crate_name::__serde::Deserializer<'de>,
+                {
+                    let alt = <#crate_name::Alt<#old, #shadow> as #crate_name::__serde::Deserialize>::deserialize(deserializer)?;
+                    Ok(match alt {
+                        #crate_name::Alt::Old(old) => {
+                            #crate_name::Compatible::<#old, #name>::from_old(old).into_current()
+                        }
+                        #crate_name::Alt::Current(current) => #name {
+                            #(#moves,)*
+                        },
+                    })
+                }
+            }
+
+            impl From<#crate_name::Compatible<#old, #name>> for #name {
+                fn from(value: #crate_name::Compatible<#old, #name>) -> Self {
+                    value.into_current()
+                }
+            }
+        };
+    })
+}
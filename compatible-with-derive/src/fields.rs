@@ -0,0 +1,429 @@
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+
+/// What `#[compatible(...)]` says about a field  
+#[derive(Default)]
+struct FieldOptions {
+    /// `old = "path::Old"`, the old type of the field
+    old: Option<syn::Type>,
+    /// `aliases("a", "b")`, names the field was stored under in older releases
+    aliases: Vec<syn::LitStr>,
+}
+
+fn field_options(field: &syn::Field) -> syn::Result<FieldOptions> {
+    let mut options = FieldOptions::default();
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("compatible"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("old") {
+                let value: syn::LitStr = meta.value()?.parse()?;
+                options.old = Some(value.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("aliases") {
+                let content;
+                syn::parenthesized!(content in meta.input);
+                let aliases = content
+                    .parse_terminated(<syn::LitStr as syn::parse::Parse>::parse, syn::Token![,])?;
+                options.aliases.extend(aliases);
+                Ok(())
+            } else {
+                Err(meta.error("expected `old = \"...\"` or `aliases(\"...\")`"))
+            }
+        })?;
+    }
+    Ok(options)
+}
+
+pub fn serde_attrs(attrs: &[syn::Attribute]) -> impl Iterator<Item = &syn::Attribute> {
+    attrs.iter().filter(|a| a.path().is_ident("serde"))
+}
+
+/// The serde attributes of an `old` field, split by what they describe  
+#[derive(Default)]
+struct OldFieldAttrs {
+    /// Items that apply to the field as a whole, such as `rename`
+    rest: Vec<proc_macro2::TokenStream>,
+    /// `with`, `deserialize_with` and `serialize_with`, which describe the current type and
+    /// can't stay on the `Compatible`
+    with: Vec<proc_macro2::TokenStream>,
+    /// What `default` or `default = "path"` would produce, also a current value
+    default: Option<proc_macro2::TokenStream>,
+}
+
+fn old_field_attrs(attrs: &[&syn::Attribute], ty: &syn::Type) -> syn::Result<OldFieldAttrs> {
+    let mut split = OldFieldAttrs::default();
+    for attr in attrs {
+        let items = attr.parse_args_with(
+            syn::punctuated::Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated,
+        )?;
+        let (mut rest, mut with) = (Vec::new(), Vec::new());
+        for item in items {
+            match item {
+                syn::Meta::Path(ref path) if path.is_ident("default") => {
+                    split.default = Some(quote! { <#ty as ::core::default::Default>::default() });
+                }
+                syn::Meta::NameValue(ref default) if default.path.is_ident("default") => {
+                    let path = match default.value {
+                        syn::Expr::Lit(syn::ExprLit {
+                            lit: syn::Lit::Str(ref path),
+                            ..
+                        }) => path.parse::<syn::ExprPath>()?,
+                        ref value => {
+                            return Err(syn::Error::new(value.span(), "expected a string path"))
+                        }
+                    };
+                    split.default = Some(quote! { #path() });
+                }
+                _ if ["with", "deserialize_with", "serialize_with"]
+                    .iter()
+                    .any(|name| item.path().is_ident(name)) =>
+                {
+                    with.push(item)
+                }
+                _ => rest.push(item),
+            }
+        }
+        if !rest.is_empty() {
+            split.rest.push(quote! { #[serde(#(#rest),*)] });
+        }
+        if !with.is_empty() {
+            split.with.push(quote! { #[serde(#(#with),*)] });
+        }
+    }
+    Ok(split)
+}
+
+/// Whether a `#[serde(rename ...)]` is already on the container  
+pub fn renamed(attrs: &[&syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| match attr.meta {
+        syn::Meta::List(ref list) => list.tokens.clone().into_iter().any(
+            |token| matches!(token, proc_macro2::TokenTree::Ident(ref ident) if ident == "rename"),
+        ),
+        _ => false,
+    })
+}
+
+pub fn derive(input: syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            input.generics.span(),
+            "CompatibleFields does not support generic structs",
+        ));
+    }
+    let fields = match input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: ref fields @ (syn::Fields::Named(_) | syn::Fields::Unnamed(_)),
+            ..
+        }) => fields,
+        syn::Data::Enum(ref data) => return derive_enum(&input, data),
+        _ => {
+            return Err(syn::Error::new(
+                name.span(),
+                "CompatibleFields can only be derived for structs with fields and enums",
+            ))
+        }
+    };
+
+    let crate_name = syn::Ident::new("compatible_with", proc_macro2::Span::call_site());
+    let serde_crate = format!("{crate_name}::__serde");
+    let shadow = format_ident!("__{}CompatibleFields", name);
+    let shadow_ref = format_ident!("__{}CompatibleFieldsRef", name);
+    let container_attrs = serde_attrs(&input.attrs).collect::<Vec<_>>();
+    // Keep the struct's own name in error messages instead of the shadow's
+    let rename = (!renamed(&container_attrs)).then(|| {
+        let rename = name.to_string();
+        quote! { #[serde(rename = #rename)] }
+    });
+
+    let mut shadow_fields = Vec::new();
+    let mut ref_fields = Vec::new();
+    let mut conversions = Vec::new();
+    let mut borrows = Vec::new();
+    let mut helpers = Vec::new();
+    for (index, field) in fields.iter().enumerate() {
+        // Tuple structs can be built with `Name { 0: .. }` too, so both kinds share the code
+        let member = match field.ident {
+            Some(ref ident) => syn::Member::Named(ident.clone()),
+            None => syn::Member::Unnamed(index.into()),
+        };
+        let ident = field.ident.as_ref().map(|ident| quote! { #ident: });
+        let ty = &field.ty;
+        let attrs = serde_attrs(&field.attrs).collect::<Vec<_>>();
+        let FieldOptions { old, aliases } = field_options(field)?;
+        let aliases = (!aliases.is_empty()).then(|| quote! { #[serde(#(alias = #aliases),*)] });
+        match old {
+            Some(old) => {
+                let OldFieldAttrs {
+                    rest,
+                    with,
+                    default,
+                } = old_field_attrs(&attrs, ty)?;
+                let (shadow_ty, from_current, conversion) = if with.is_empty() {
+                    (
+                        quote! { #crate_name::Compatible<#old, #ty> },
+                        quote! { #crate_name::Compatible::from_current },
+                        quote! { shadow.#member.into_current() },
+                    )
+                } else {
+                    // The current shape is read through a newtype carrying the field's `with`
+                    let wrapper = format_ident!("__{}CompatibleFields{}", name, index);
+                    helpers.push(quote! {
+                        #[derive(#crate_name::__serde::Deserialize)]
+                        #[serde(crate = #serde_crate)]
+                        struct #wrapper(#(#with)* #ty);
+                    });
+                    (
+                        quote! { #crate_name::Alt<#old, #wrapper> },
+                        quote! { |current| #crate_name::Alt::Current(#wrapper(current)) },
+                        quote! {
+                            match shadow.#member {
+                                #crate_name::Alt::Old(old) => {
+                                    #crate_name::Compatible::<#old, #ty>::from_old(old)
+                                        .into_current()
+                                }
+                                #crate_name::Alt::Current(current) => current.0,
+                            }
+                        },
+                    )
+                };
+                // A default is a current value, so it gets wrapped to fit the shadow field
+                let default = default.map(|default| {
+                    let function = format!("__compatible_fields_default_{index}");
+                    let function_ident = format_ident!("{}", function);
+                    helpers.push(quote! {
+                        fn #function_ident() -> #shadow_ty {
+                            (#from_current)(#default)
+                        }
+                    });
+                    quote! { #[serde(default = #function)] }
+                });
+                shadow_fields.push(quote! { #(#rest)* #default #aliases #ident #shadow_ty });
+                conversions.push(quote! { #member: #conversion });
+            }
+            None => {
+                shadow_fields.push(quote! { #(#attrs)* #aliases #ident #ty });
+                conversions.push(quote! { #member: shadow.#member });
+            }
+        }
+        ref_fields.push(quote! { #(#attrs)* #ident &'a #ty });
+        borrows.push(quote! { #member: &self.#member });
+    }
+    let (shadow_body, ref_body) = match fields {
+        syn::Fields::Named(_) => (
+            quote! { { #(#shadow_fields,)* } },
+            quote! { { #(#ref_fields,)* } },
+        ),
+        _ => (
+            quote! { ( #(#shadow_fields,)* ); },
+            quote! { ( #(#ref_fields,)* ); },
+        ),
+    };
+
+    Ok(quote! {
+        const _: () = {
+            #(#helpers)*
+
+            #[derive(#crate_name::__serde::Deserialize)]
+            #[serde(crate = #serde_crate)]
+            #rename
+            #(#container_attrs)*
+            struct #shadow #shadow_body
+
+            #[derive(#crate_name::__serde::Serialize)]
+            #[serde(crate = #serde_crate)]
+            #rename
+            #(#container_attrs)*
+            struct #shadow_ref<'a> #ref_body
+
+            impl<'de> #crate_name::__serde::Deserialize<'de> for #name {
+                fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                where
+                    D: #crate_name::__serde::Deserializer<'de>,
+                {
+                    let shadow = <#shadow as #crate_name::__serde::Deserialize>::deserialize(deserializer)?;
+                    Ok(#name {
+                        #(#conversions,)*
+                    })
+                }
+            }
+
+            impl #crate_name::__serde::Serialize for #name {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: #crate_name::__serde::Serializer,
+                {
+                    #crate_name::__serde::Serialize::serialize(
+                        &#shadow_ref {
+                            #(#borrows,)*
+                        },
+                        serializer,
+                    )
+                }
+            }
+        };
+    })
+}
+
+/// The rule from `#[compatible(variant_rename_all = "...")]` on an enum  
+fn variant_rename_all(input: &syn::DeriveInput) -> syn::Result<syn::LitStr> {
+    let mut rule = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("compatible"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("variant_rename_all") {
+                rule = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `variant_rename_all = \"...\"`"))
+            }
+        })?;
+    }
+    rule.ok_or_else(|| {
+        syn::Error::new(
+            input.ident.span(),
+            "CompatibleFields on an enum needs `#[compatible(variant_rename_all = \"...\")]`",
+        )
+    })
+}
+
+/// Enums whose variant fields got renamed, the old shape is the enum with its fields as written
+/// and the current shape applies `rename_all_fields`  
+fn derive_enum(
+    input: &syn::DeriveInput,
+    data: &syn::DataEnum,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let rule = variant_rename_all(input)?;
+    let crate_name = syn::Ident::new("compatible_with", proc_macro2::Span::call_site());
+    let serde_crate = format!("{crate_name}::__serde");
+    let old = format_ident!("__{}CompatibleFieldsOld", name);
+    let current = format_ident!("__{}CompatibleFieldsCurrent", name);
+    let shadow_ref = format_ident!("__{}CompatibleFieldsRef", name);
+    let container_attrs = serde_attrs(&input.attrs).collect::<Vec<_>>();
+    let rename = (!renamed(&container_attrs)).then(|| {
+        let rename = name.to_string();
+        quote! { #[serde(rename = #rename)] }
+    });
+
+    let mut variants = Vec::new();
+    let mut ref_variants = Vec::new();
+    let mut patterns = Vec::new();
+    for variant in &data.variants {
+        let ident = &variant.ident;
+        let attrs = serde_attrs(&variant.attrs);
+        let fields = variant.fields.iter().map(|field| {
+            let attrs = serde_attrs(&field.attrs).collect::<Vec<_>>();
+            let (ident, ty) = (&field.ident, &field.ty);
+            let colon = field.ident.as_ref().map(|_| quote! { : });
+            (
+                quote! { #(#attrs)* #ident #colon #ty },
+                quote! { #(#attrs)* #ident #colon &'a #ty },
+            )
+        });
+        let (fields, ref_fields): (Vec<_>, Vec<_>) = fields.unzip();
+        let bindings = variant
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                field
+                    .ident
+                    .clone()
+                    .unwrap_or_else(|| format_ident!("__{}", i))
+            })
+            .collect::<Vec<_>>();
+        let (body, ref_body, pattern) = match variant.fields {
+            syn::Fields::Named(_) => (
+                quote! { { #(#fields,)* } },
+                quote! { { #(#ref_fields,)* } },
+                quote! { { #(#bindings,)* } },
+            ),
+            syn::Fields::Unnamed(_) => (
+                quote! { ( #(#fields,)* ) },
+                quote! { ( #(#ref_fields,)* ) },
+                quote! { ( #(#bindings,)* ) },
+            ),
+            syn::Fields::Unit => (quote! {}, quote! {}, quote! {}),
+        };
+        let attrs = attrs.collect::<Vec<_>>();
+        variants.push(quote! { #(#attrs)* #ident #body });
+        ref_variants.push(quote! { #(#attrs)* #ident #ref_body });
+        patterns.push((ident, pattern));
+    }
+    let from_old = patterns
+        .iter()
+        .map(|(ident, pattern)| quote! { #old::#ident #pattern => #name::#ident #pattern });
+    let from_current = patterns
+        .iter()
+        .map(|(ident, pattern)| quote! { #current::#ident #pattern => #name::#ident #pattern });
+    let borrows = patterns
+        .iter()
+        .map(|(ident, pattern)| quote! { #name::#ident #pattern => #shadow_ref::#ident #pattern });
+
+    Ok(quote! {
+        const _: () = {
+            #[derive(#crate_name::__serde::Deserialize)]
+            #[serde(crate = #serde_crate)]
+            #rename
+            #(#container_attrs)*
+            enum #old {
+                #(#variants,)*
+            }
+
+            #[derive(#crate_name::__serde::Deserialize)]
+            #[serde(crate = #serde_crate, rename_all_fields = #rule)]
+            #rename
+            #(#container_attrs)*
+            enum #current {
+                #(#variants,)*
+            }
+
+            #[derive(#crate_name::__serde::Serialize)]
+            #[serde(crate = #serde_crate, rename_all_fields = #rule)]
+            #rename
+            #(#container_attrs)*
+            enum #shadow_ref<'a> {
+                #(#ref_variants,)*
+                #[serde(skip)]
+                #[allow(dead_code)]
+                __Unused(core::marker::PhantomData<&'a ()>),
+            }
+
+            impl<'de> #crate_name::__serde::Deserialize<'de> for #name {
+                fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                where
+                    D: #crate_name::__serde::Deserializer<'de>,
+                {
+                    let shadow = <#crate_name::Alt<#old, #current> as #crate_name::__serde::Deserialize>::deserialize(deserializer)?;
+                    Ok(match shadow {
+                        #crate_name::Alt::Old(old) => match old {
+                            #(#from_old,)*
+                        },
+                        #crate_name::Alt::Current(current) => match current {
+                            #(#from_current,)*
+                        },
+                    })
+                }
+            }
+
+            impl #crate_name::__serde::Serialize for #name {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: #crate_name::__serde::Serializer,
+                {
+                    let shadow = match self {
+                        #(#borrows,)*
+                    };
+                    #crate_name::__serde::Serialize::serialize(&shadow, serializer)
+                }
+            }
+        };
+    })
+}
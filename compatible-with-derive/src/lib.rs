@@ -1,20 +1,58 @@
 use proc_macro::TokenStream;
 use syn::parse_macro_input;
 
-#[proc_macro_derive(CompatibleWith)]
+mod attribute;
+mod fields;
+mod versioned;
+mod with;
+
+/// Implements `From<Compatible<Old, Self>>` for use with `#[serde(from = "...")]`  
+/// With `#[compatible(validate = "path::to::fn")]` it implements `TryFrom` instead, for
+/// `#[serde(try_from = "...")]`, and every converted value has to pass
+/// `fn(&Self) -> Result<(), impl Display>`  
+#[proc_macro_derive(CompatibleWith, attributes(compatible))]
 pub fn derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as syn::DeriveInput);
-    let name = input.ident;
-    let crate_name = syn::Ident::new("compatible_with", proc_macro2::Span::call_site());
-    quote::quote! {
-        impl<Old> From<#crate_name::Compatible<Old, #name>> for #name
-        where
-            Self: #crate_name::CompatibleWith<Old>,
-        {
-            fn from(value: #crate_name::Compatible<Old, #name>) -> Self {
-                value.into_current()
-            }
-        }
-    }
-    .into()
+    with::derive(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Generates `Serialize` and `Deserialize` for a struct where every field marked
+/// `#[compatible(old = "path::Old")]` also accepts its old shape  
+/// Fields are always serialized in their current shape and `#[serde]` attributes are kept  
+/// A `with`, `deserialize_with` or `serialize_with` on an `old` field applies to its current
+/// shape, the old shape is read with its own `Deserialize`  
+/// `default` and `default = "path"` on an `old` field give a current value for records written
+/// before the field existed  
+/// `#[compatible(aliases("old_name", "older_name"))]` also reads a field under names it was
+/// stored as in older releases  
+/// On an enum, `#[compatible(variant_rename_all = "camelCase")]` reads variant fields both as
+/// written and renamed, and always writes them renamed  
+#[proc_macro_derive(CompatibleFields, attributes(compatible, serde))]
+pub fn derive_fields(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+    fields::derive(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements `Versioned` from `#[compatible(version = 2)]`  
+#[proc_macro_derive(Versioned, attributes(compatible))]
+pub fn derive_versioned(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+    versioned::derive(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// `#[compatible_with(old = "path::Old")]` on the current struct implements `Deserialize` for
+/// both the old and the current shape, old data goes through `From<Old>`  
+/// Derive `Serialize` as usual, it always writes the current shape, but not `Deserialize`  
+#[proc_macro_attribute]
+pub fn compatible_with(args: TokenStream, input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+    attribute::expand(args.into(), input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
@@ -0,0 +1,35 @@
+use quote::quote;
+
+pub fn derive(input: syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut version = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("compatible"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("version") {
+                let value: syn::LitInt = meta.value()?.parse()?;
+                version = Some(value.base10_parse::<u32>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `version = ...`"))
+            }
+        })?;
+    }
+    let Some(version) = version else {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "Versioned needs a `#[compatible(version = ...)]` attribute",
+        ));
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let crate_name = syn::Ident::new("compatible_with", proc_macro2::Span::call_site());
+    Ok(quote! {
+        impl #impl_generics #crate_name::Versioned for #name #ty_generics #where_clause {
+            const VERSION: u32 = #version;
+        }
+    })
+}
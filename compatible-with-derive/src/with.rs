@@ -0,0 +1,61 @@
+use quote::quote;
+
+pub fn derive(input: syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut validate = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("compatible"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("validate") {
+                let value: syn::LitStr = meta.value()?.parse()?;
+                validate = Some(value.parse::<syn::ExprPath>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `validate = \"...\"`"))
+            }
+        })?;
+    }
+
+    let name = &input.ident;
+    let crate_name = syn::Ident::new("compatible_with", proc_macro2::Span::call_site());
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+    // The old type is a parameter of the impl, next to the struct's own generics
+    let mut generics = input.generics.clone();
+    generics.params.push(syn::parse_quote!(__Old));
+    let (impl_generics, _, _) = generics.split_for_impl();
+    let predicates = where_clause.map(|clause| &clause.predicates);
+    let current = quote! { #name #ty_generics };
+    let Some(validate) = validate else {
+        return Ok(quote! {
+            impl #impl_generics From<#crate_name::Compatible<__Old, #current>> for #current
+            where
+                Self: #crate_name::CompatibleWith<__Old>,
+                #predicates
+            {
+                fn from(value: #crate_name::Compatible<__Old, #current>) -> Self {
+                    value.into_current()
+                }
+            }
+        });
+    };
+
+    Ok(quote! {
+        impl #impl_generics TryFrom<#crate_name::Compatible<__Old, #current>> for #current
+        where
+            Self: #crate_name::CompatibleWith<__Old>,
+            #predicates
+        {
+            type Error = String;
+
+            fn try_from(value: #crate_name::Compatible<__Old, #current>) -> Result<Self, Self::Error> {
+                let current = value.into_current();
+                match #validate(&current) {
+                    Ok(()) => Ok(current),
+                    Err(err) => Err(err.to_string()),
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,106 @@
+//! Upgrades a file of JSON records to their current shape  
+//! `cargo run --example migrate --features json -- user users.ndjson [upgraded.ndjson]`  
+//! Records can be newline delimited or simply concatenated, the output is one record per line  
+use compatible_with::json::MigrationRegistry;
+use serde::*;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OldUser {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct User {
+    pub name: String,
+    pub admin: bool,
+}
+
+impl From<OldUser> for User {
+    fn from(old: OldUser) -> Self {
+        User {
+            name: old.name,
+            admin: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Port(u16);
+
+impl From<String> for Port {
+    fn from(old: String) -> Self {
+        Port(old.parse().unwrap_or(80))
+    }
+}
+
+pub fn registry() -> MigrationRegistry {
+    let mut registry = MigrationRegistry::new();
+    registry
+        .register::<OldUser, User>("user")
+        .register::<String, Port>("port");
+    registry
+}
+
+/// Streams every record in `input` through the `kind` migration into `output` and returns how
+/// many records were written  
+pub fn migrate(
+    registry: &MigrationRegistry,
+    kind: &str,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> Result<usize, serde_json::Error> {
+    let mut count = 0;
+    for record in serde_json::Deserializer::from_reader(input).into_iter() {
+        let current = registry.migrate(kind, record?)?;
+        serde_json::to_writer(&mut output, &current)?;
+        output.write_all(b"\n").map_err(serde_json::Error::io)?;
+        count += 1;
+    }
+    output.flush().map_err(serde_json::Error::io)?;
+    Ok(count)
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let (Some(kind), Some(input)) = (args.next(), args.next()) else {
+        return Err("usage: migrate <kind> <input> [output]".into());
+    };
+    let registry = registry();
+    if !registry.contains(&kind) {
+        return Err(format!("unknown record kind `{kind}`").into());
+    }
+
+    let input = BufReader::new(File::open(input)?);
+    let count = match args.next() {
+        Some(output) => migrate(
+            &registry,
+            &kind,
+            input,
+            BufWriter::new(File::create(output)?),
+        )?,
+        None => migrate(&registry, &kind, input, io::stdout().lock())?,
+    };
+    eprintln!("migrated {count} records");
+    Ok(())
+}
+
+#[test]
+pub fn test_migrate() {
+    let input = "{\"name\":\"a\"}\n{\"name\":\"b\",\"admin\":true}\n";
+    let mut output = Vec::new();
+    let count = migrate(&registry(), "user", input.as_bytes(), &mut output).unwrap();
+
+    assert_eq!(count, 2);
+    // The registry goes through `Value`, which only keeps the field order with `preserve-order`
+    #[cfg(not(feature = "preserve-order"))]
+    let expected = "{\"admin\":false,\"name\":\"a\"}\n{\"admin\":true,\"name\":\"b\"}\n";
+    #[cfg(feature = "preserve-order")]
+    let expected = "{\"name\":\"a\",\"admin\":false}\n{\"name\":\"b\",\"admin\":true}\n";
+    assert_eq!(String::from_utf8(output).unwrap(), expected);
+
+    let mut output = Vec::new();
+    assert!(migrate(&registry(), "port", "\"80\" []".as_bytes(), &mut output).is_err());
+}
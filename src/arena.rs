@@ -0,0 +1,60 @@
+use super::{Alt, Compatible};
+
+/// Like [`CompatibleWith`](crate::CompatibleWith) for current types that live in an arena, the
+/// conversion allocates into `arena` instead of the heap  
+pub trait CompatibleWithArena<'arena, Old>: Sized {
+    type Arena: ?Sized;
+    fn from_old_in(value: Old, arena: &'arena Self::Arena) -> Self;
+}
+
+impl<Old, Current> Compatible<Old, Current> {
+    /// Converts an old value into `arena`, a current value is returned as is  
+    pub fn into_current_in<'arena>(self, arena: &'arena Current::Arena) -> Current
+    where
+        Current: CompatibleWithArena<'arena, Old>,
+    {
+        match self.0 {
+            Alt::Old(old) => {
+                super::counters::record();
+                Current::from_old_in(old, arena)
+            }
+            Alt::Current(current) => current,
+        }
+    }
+}
+
+#[test]
+pub fn test_into_current_in() {
+    use std::cell::OnceCell;
+
+    /// Fixed capacity bump arena, slots are filled front to back and never freed
+    pub struct Bump(Vec<OnceCell<String>>);
+
+    impl Bump {
+        pub fn alloc(&self, value: &str) -> &str {
+            let slot = self.0.iter().find(|slot| slot.get().is_none()).unwrap();
+            slot.get_or_init(|| value.to_owned())
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub struct Name<'arena>(&'arena str);
+
+    impl<'arena> CompatibleWithArena<'arena, (String, String)> for Name<'arena> {
+        type Arena = Bump;
+        fn from_old_in((first, last): (String, String), arena: &'arena Bump) -> Self {
+            Name(arena.alloc(&format!("{first} {last}")))
+        }
+    }
+
+    let arena = Bump((0..2).map(|_| OnceCell::new()).collect());
+    let names = [
+        Compatible::from_old(("Jane".into(), "Doe".into())),
+        Compatible::from_current(Name("John Roe")),
+        Compatible::from_old(("Ann".into(), "Lee".into())),
+    ]
+    .map(|name| name.into_current_in(&arena));
+
+    assert_eq!(names, [Name("Jane Doe"), Name("John Roe"), Name("Ann Lee")]);
+    assert!(arena.0.iter().all(|slot| slot.get().is_some()));
+}
@@ -0,0 +1,86 @@
+//! Binary fields that used to be stored as base64 strings, enabled with the `base64` feature  
+use super::Compatible;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+/// Bytes written as a standard, padded base64 string  
+/// Reading goes through `base64`'s `STANDARD` engine, so missing or extra padding and
+/// non-canonical trailing bits are rejected  
+#[derive(PartialEq, PartialOrd, Ord, Eq, Debug, Hash, Clone, Default)]
+pub struct Base64(pub Vec<u8>);
+
+impl Base64 {
+    pub fn decode(encoded: &str) -> Option<Self> {
+        STANDARD.decode(encoded).ok().map(Base64)
+    }
+
+    pub fn encode(&self) -> String {
+        STANDARD.encode(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Base64 {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        let encoded = String::deserialize(deserializer)?;
+        Base64::decode(&encoded).ok_or_else(|| serde::de::Error::custom("invalid base64 string"))
+    }
+}
+
+impl Serialize for Base64 {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(&self.encode())
+    }
+}
+
+impl From<Base64> for Vec<u8> {
+    fn from(old: Base64) -> Self {
+        old.0
+    }
+}
+
+impl From<Vec<u8>> for Base64 {
+    fn from(current: Vec<u8>) -> Self {
+        Base64(current)
+    }
+}
+
+/// A binary field that used to be stored as a [`Base64`] string  
+pub type CompatibleBytes<Current = Vec<u8>> = Compatible<Base64, Current>;
+
+#[test]
+pub fn test_bytes() {
+    #[derive(Deserialize)]
+    pub struct Blob {
+        pub data: CompatibleBytes,
+    }
+
+    let old: Blob = serde_json::from_str(r#"{"data":"aGVsbG8="}"#).unwrap();
+    assert_eq!(old.data.into_current(), b"hello");
+
+    let padded: CompatibleBytes = serde_json::from_str(r#""aGk=""#).unwrap();
+    assert_eq!(padded.into_current(), b"hi");
+    // Missing or extra padding and stray trailing bits aren't canonical base64
+    assert_eq!(Base64::decode("aGk"), None);
+    assert_eq!(Base64::decode("aGk==="), None);
+    assert_eq!(Base64::decode("aGl="), None);
+
+    let current: CompatibleBytes = serde_json::from_str("[1,2,3]").unwrap();
+    assert_eq!(current.into_current(), [1, 2, 3]);
+
+    assert!(serde_json::from_str::<CompatibleBytes>(r#""not base64!""#).is_err());
+    for bytes in [&b""[..], b"a", b"ab", b"abc", b"abcd"] {
+        let encoded = Base64(bytes.to_vec()).encode();
+        assert_eq!(Base64::decode(&encoded), Some(Base64(bytes.to_vec())));
+    }
+    assert_eq!(
+        serde_json::to_string(&Base64(b"hello".to_vec())).unwrap(),
+        r#""aGVsbG8=""#
+    );
+}
@@ -0,0 +1,530 @@
+//! The buffer behind the untagged [`Alt`](crate::Alt) impl  
+//! The input is read into a [`Content`] tree once and both shapes are tried against borrows of
+//! it, with an error type that never formats a message since untagged matching throws the per
+//! shape errors away anyway  
+use core::fmt;
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+
+pub(crate) enum Content<'de> {
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    I128(i128),
+    F32(f32),
+    F64(f64),
+    Char(char),
+    String(String),
+    Str(&'de str),
+    ByteBuf(Vec<u8>),
+    Bytes(&'de [u8]),
+    None,
+    Some(Box<Content<'de>>),
+    Unit,
+    Newtype(Box<Content<'de>>),
+    Seq(Vec<Content<'de>>),
+    Map(Vec<(Content<'de>, Content<'de>)>),
+}
+
+impl<'de> Deserialize<'de> for Content<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ContentVisitor)
+    }
+}
+
+struct ContentVisitor;
+
+impl<'de> Visitor<'de> for ContentVisitor {
+    type Value = Content<'de>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(Content::Bool(v))
+    }
+
+    fn visit_i8<E>(self, v: i8) -> Result<Self::Value, E> {
+        Ok(Content::I8(v))
+    }
+
+    fn visit_i16<E>(self, v: i16) -> Result<Self::Value, E> {
+        Ok(Content::I16(v))
+    }
+
+    fn visit_i32<E>(self, v: i32) -> Result<Self::Value, E> {
+        Ok(Content::I32(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(Content::I64(v))
+    }
+
+    fn visit_i128<E>(self, v: i128) -> Result<Self::Value, E> {
+        Ok(Content::I128(v))
+    }
+
+    fn visit_u8<E>(self, v: u8) -> Result<Self::Value, E> {
+        Ok(Content::U8(v))
+    }
+
+    fn visit_u16<E>(self, v: u16) -> Result<Self::Value, E> {
+        Ok(Content::U16(v))
+    }
+
+    fn visit_u32<E>(self, v: u32) -> Result<Self::Value, E> {
+        Ok(Content::U32(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Content::U64(v))
+    }
+
+    fn visit_u128<E>(self, v: u128) -> Result<Self::Value, E> {
+        Ok(Content::U128(v))
+    }
+
+    fn visit_f32<E>(self, v: f32) -> Result<Self::Value, E> {
+        Ok(Content::F32(v))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(Content::F64(v))
+    }
+
+    fn visit_char<E>(self, v: char) -> Result<Self::Value, E> {
+        Ok(Content::Char(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(Content::String(v.into()))
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E> {
+        Ok(Content::Str(v))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+        Ok(Content::String(v))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(Content::ByteBuf(v.into()))
+    }
+
+    fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+        Ok(Content::Bytes(v))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(Content::ByteBuf(v))
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(Content::None)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Content::deserialize(deserializer).map(|v| Content::Some(Box::new(v)))
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(Content::Unit)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Content::deserialize(deserializer).map(|v| Content::Newtype(Box::new(v)))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Content::Seq(values))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0).min(4096));
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(Content::Map(entries))
+    }
+}
+
+/// Why a buffered shape didn't match, without the message  
+#[derive(Debug)]
+pub(crate) struct Mismatch;
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("buffered value did not match")
+    }
+}
+
+impl std::error::Error for Mismatch {}
+
+impl de::Error for Mismatch {
+    fn custom<T: fmt::Display>(_msg: T) -> Self {
+        Mismatch
+    }
+}
+
+/// Replays a borrowed [`Content`] and reports the original deserializer's
+/// `is_human_readable`  
+#[derive(Clone, Copy)]
+pub(crate) struct ContentRef<'a, 'de> {
+    content: &'a Content<'de>,
+    human_readable: bool,
+}
+
+impl<'a, 'de> ContentRef<'a, 'de> {
+    pub(crate) fn new(content: &'a Content<'de>, human_readable: bool) -> Self {
+        ContentRef {
+            content,
+            human_readable,
+        }
+    }
+
+    fn child(self, content: &'a Content<'de>) -> Self {
+        ContentRef { content, ..self }
+    }
+}
+
+impl<'a, 'de> Deserializer<'de> for ContentRef<'a, 'de> {
+    type Error = Mismatch;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Mismatch>
+    where
+        V: Visitor<'de>,
+    {
+        match *self.content {
+            Content::Bool(v) => visitor.visit_bool(v),
+            Content::U8(v) => visitor.visit_u8(v),
+            Content::U16(v) => visitor.visit_u16(v),
+            Content::U32(v) => visitor.visit_u32(v),
+            Content::U64(v) => visitor.visit_u64(v),
+            Content::U128(v) => visitor.visit_u128(v),
+            Content::I8(v) => visitor.visit_i8(v),
+            Content::I16(v) => visitor.visit_i16(v),
+            Content::I32(v) => visitor.visit_i32(v),
+            Content::I64(v) => visitor.visit_i64(v),
+            Content::I128(v) => visitor.visit_i128(v),
+            Content::F32(v) => visitor.visit_f32(v),
+            Content::F64(v) => visitor.visit_f64(v),
+            Content::Char(v) => visitor.visit_char(v),
+            Content::String(ref v) => visitor.visit_str(v),
+            Content::Str(v) => visitor.visit_borrowed_str(v),
+            Content::ByteBuf(ref v) => visitor.visit_bytes(v),
+            Content::Bytes(v) => visitor.visit_borrowed_bytes(v),
+            Content::None => visitor.visit_none(),
+            Content::Some(ref v) => visitor.visit_some(self.child(v)),
+            Content::Unit => visitor.visit_unit(),
+            Content::Newtype(ref v) => visitor.visit_newtype_struct(self.child(v)),
+            Content::Seq(ref values) => {
+                let mut seq = SeqRef {
+                    parent: self,
+                    values: values.iter(),
+                };
+                let value = visitor.visit_seq(&mut seq)?;
+                // Leftover elements mean the visitor wanted a shorter sequence
+                match seq.values.len() {
+                    0 => Ok(value),
+                    _ => Err(Mismatch),
+                }
+            }
+            Content::Map(ref entries) => {
+                let mut map = MapRef {
+                    parent: self,
+                    entries: entries.iter(),
+                    value: None,
+                };
+                let value = visitor.visit_map(&mut map)?;
+                match map.entries.len() {
+                    0 => Ok(value),
+                    _ => Err(Mismatch),
+                }
+            }
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Mismatch>
+    where
+        V: Visitor<'de>,
+    {
+        match *self.content {
+            Content::None => visitor.visit_none(),
+            Content::Some(ref v) => visitor.visit_some(self.child(v)),
+            Content::Unit => visitor.visit_unit(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Mismatch>
+    where
+        V: Visitor<'de>,
+    {
+        match *self.content {
+            Content::Newtype(ref v) => visitor.visit_newtype_struct(self.child(v)),
+            // Self-describing formats usually write newtypes as their inner value
+            _ => visitor.visit_newtype_struct(self),
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Mismatch>
+    where
+        V: Visitor<'de>,
+    {
+        let (variant, value) = match *self.content {
+            Content::Map(ref entries) => match entries.as_slice() {
+                [(variant, value)] => (variant, Some(value)),
+                _ => return Err(Mismatch),
+            },
+            Content::String(_) | Content::Str(_) => (self.content, None),
+            _ => return Err(Mismatch),
+        };
+        visitor.visit_enum(EnumRef {
+            parent: self,
+            variant,
+            value,
+        })
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Mismatch>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.human_readable
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+struct SeqRef<'a, 'de> {
+    parent: ContentRef<'a, 'de>,
+    values: core::slice::Iter<'a, Content<'de>>,
+}
+
+impl<'a, 'de> de::SeqAccess<'de> for SeqRef<'a, 'de> {
+    type Error = Mismatch;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Mismatch>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        self.values
+            .next()
+            .map(|value| seed.deserialize(self.parent.child(value)))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
+
+struct MapRef<'a, 'de> {
+    parent: ContentRef<'a, 'de>,
+    entries: core::slice::Iter<'a, (Content<'de>, Content<'de>)>,
+    value: Option<&'a Content<'de>>,
+}
+
+impl<'a, 'de> de::MapAccess<'de> for MapRef<'a, 'de> {
+    type Error = Mismatch;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Mismatch>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(self.parent.child(key)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Mismatch>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let value = self.value.take().ok_or(Mismatch)?;
+        seed.deserialize(self.parent.child(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+struct EnumRef<'a, 'de> {
+    parent: ContentRef<'a, 'de>,
+    variant: &'a Content<'de>,
+    value: Option<&'a Content<'de>>,
+}
+
+impl<'a, 'de> de::EnumAccess<'de> for EnumRef<'a, 'de> {
+    type Error = Mismatch;
+    type Variant = VariantRef<'a, 'de>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Mismatch>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(self.parent.child(self.variant))?;
+        Ok((
+            variant,
+            VariantRef {
+                parent: self.parent,
+                value: self.value,
+            },
+        ))
+    }
+}
+
+struct VariantRef<'a, 'de> {
+    parent: ContentRef<'a, 'de>,
+    value: Option<&'a Content<'de>>,
+}
+
+impl<'a, 'de> de::VariantAccess<'de> for VariantRef<'a, 'de> {
+    type Error = Mismatch;
+
+    fn unit_variant(self) -> Result<(), Mismatch> {
+        match self.value {
+            None | Some(Content::Unit) => Ok(()),
+            Some(_) => Err(Mismatch),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Mismatch>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        let value = self.value.ok_or(Mismatch)?;
+        seed.deserialize(self.parent.child(value))
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Mismatch>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Some(value @ Content::Seq(_)) => self.parent.child(value).deserialize_any(visitor),
+            _ => Err(Mismatch),
+        }
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Mismatch>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Some(value @ (Content::Seq(_) | Content::Map(_))) => {
+                self.parent.child(value).deserialize_any(visitor)
+            }
+            _ => Err(Mismatch),
+        }
+    }
+}
+
+#[test]
+pub fn test_buffered_alt() {
+    use crate::Alt;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    pub enum Kind {
+        Unit,
+        Pair(u8, u8),
+        Named { a: u8 },
+    }
+
+    // A longer sequence must not satisfy the shorter old tuple
+    let alt: Alt<(u8, u8), Vec<u8>> = serde_json::from_str("[1,2,3]").unwrap();
+    assert_eq!(alt, Alt::Current(vec![1, 2, 3]));
+    let alt: Alt<(u8, u8), Vec<u8>> = serde_json::from_str("[1,2]").unwrap();
+    assert_eq!(alt, Alt::Old((1, 2)));
+
+    // Strings without escapes stay borrowed from the input
+    let alt: Alt<u8, &str> = serde_json::from_str(r#""a""#).unwrap();
+    assert_eq!(alt, Alt::Current("a"));
+
+    let kinds: Vec<Alt<Kind, String>> =
+        serde_json::from_str(r#"["Unit",{"Pair":[1,2]},{"Named":{"a":3}},"Other"]"#).unwrap();
+    assert_eq!(
+        kinds,
+        [
+            Alt::Old(Kind::Unit),
+            Alt::Old(Kind::Pair(1, 2)),
+            Alt::Old(Kind::Named { a: 3 }),
+            Alt::Current("Other".into()),
+        ]
+    );
+
+    // 128-bit integers are buffered as they are, serde_json only produces them with
+    // arbitrary_precision so feed them in directly
+    use serde::de::{value, IntoDeserializer};
+    let alt = Alt::<String, u128>::deserialize(
+        IntoDeserializer::<value::Error>::into_deserializer(u128::MAX),
+    )
+    .unwrap();
+    assert_eq!(alt, Alt::Current(u128::MAX));
+    let alt = Alt::<String, i128>::deserialize(
+        IntoDeserializer::<value::Error>::into_deserializer(i128::MIN),
+    )
+    .unwrap();
+    assert_eq!(alt, Alt::Current(i128::MIN));
+
+    let alt: Alt<Option<u8>, String> = serde_json::from_str("null").unwrap();
+    assert_eq!(alt, Alt::Old(None));
+
+    let err = serde_json::from_str::<Alt<u8, bool>>(r#""a""#).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("data did not match any variant of untagged enum Alt"));
+}
@@ -0,0 +1,24 @@
+//! A global count of old to current conversions, enabled with the `counters` feature  
+//! Meant for benchmarks and backfills that want to know how much legacy data a run touched  
+#[cfg(feature = "counters")]
+use core::sync::atomic::{AtomicU64, Ordering};
+
+#[cfg(feature = "counters")]
+static CONVERSIONS: AtomicU64 = AtomicU64::new(0);
+
+/// How many old values have been converted since start up or the last reset  
+#[cfg(feature = "counters")]
+pub fn conversion_count() -> u64 {
+    CONVERSIONS.load(Ordering::Relaxed)
+}
+
+#[cfg(feature = "counters")]
+pub fn reset_conversion_count() {
+    CONVERSIONS.store(0, Ordering::Relaxed);
+}
+
+#[inline]
+pub(crate) fn record() {
+    #[cfg(feature = "counters")]
+    CONVERSIONS.fetch_add(1, Ordering::Relaxed);
+}
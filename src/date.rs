@@ -0,0 +1,53 @@
+use super::Compatible;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Seconds since the unix epoch, the usual shape of legacy date fields  
+#[derive(Serialize, Deserialize, PartialEq, PartialOrd, Ord, Eq, Debug, Hash, Clone, Copy)]
+#[serde(transparent)]
+pub struct UnixTimestamp(pub i64);
+
+impl UnixTimestamp {
+    pub fn as_secs(&self) -> i64 {
+        self.0
+    }
+}
+
+impl From<UnixTimestamp> for SystemTime {
+    fn from(old: UnixTimestamp) -> Self {
+        let offset = Duration::from_secs(old.0.unsigned_abs());
+        if old.0 < 0 {
+            UNIX_EPOCH - offset
+        } else {
+            UNIX_EPOCH + offset
+        }
+    }
+}
+
+/// A date field that used to be stored as a [`UnixTimestamp`]  
+/// For a chrono `DateTime` that may also have been stored as an RFC 2822 or RFC 3339 string,
+/// use `CompatibleDateTime` from the `chrono` feature  
+pub type CompatibleDate<Current = SystemTime> = Compatible<UnixTimestamp, Current>;
+
+#[test]
+pub fn test_date() {
+    use serde::*;
+    #[derive(Deserialize)]
+    pub struct Event {
+        pub at: CompatibleDate,
+    }
+
+    let old: Event = serde_json::from_str(r#"{"at":1700000000}"#).unwrap();
+    let at = old.at.into_current();
+    assert_eq!(at, UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+
+    let current = serde_json::to_string(&at).unwrap();
+    let current: Event = serde_json::from_str(&format!(r#"{{"at":{current}}}"#)).unwrap();
+    assert_eq!(current.at.into_current(), at);
+
+    let before_epoch: CompatibleDate = serde_json::from_str("-10").unwrap();
+    assert_eq!(
+        before_epoch.into_current(),
+        UNIX_EPOCH - Duration::from_secs(10)
+    );
+}
@@ -0,0 +1,94 @@
+//! Date fields that are now a chrono [`DateTime<Utc>`], enabled with the `chrono` feature  
+//! Older data stored dates as unix seconds, RFC 2822 strings such as
+//! `"Tue, 14 Nov 2023 22:13:20 +0000"` or RFC 3339 strings, [`OldDate`] accepts all three and
+//! normalizes them to UTC  
+//! A current value serializes as an RFC 3339 string, which reads back through the text shape
+//! and parses to the same instant  
+use super::{Compatible, UnixTimestamp};
+use chrono::{DateTime, Utc};
+use core::fmt;
+use serde::{Deserialize, Serialize};
+
+/// The shapes a legacy date field was stored in  
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[serde(untagged)]
+pub enum OldDate {
+    Timestamp(UnixTimestamp),
+    Text(String),
+}
+
+/// Returned when an [`OldDate`] is out of range or not in a known string format  
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct DateParseError(OldDate);
+
+impl DateParseError {
+    pub fn old(&self) -> &OldDate {
+        &self.0
+    }
+}
+
+impl fmt::Display for DateParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            OldDate::Timestamp(secs) => {
+                write!(f, "unix timestamp {} is out of range", secs.as_secs())
+            }
+            OldDate::Text(text) => {
+                write!(f, "{text:?} is neither an RFC 3339 nor an RFC 2822 date")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DateParseError {}
+
+impl TryFrom<OldDate> for DateTime<Utc> {
+    type Error = DateParseError;
+
+    fn try_from(old: OldDate) -> Result<Self, Self::Error> {
+        let parsed = match &old {
+            OldDate::Timestamp(secs) => DateTime::from_timestamp(secs.as_secs(), 0),
+            OldDate::Text(text) => DateTime::parse_from_rfc3339(text)
+                .or_else(|_| DateTime::parse_from_rfc2822(text))
+                .ok()
+                .map(|date| date.with_timezone(&Utc)),
+        };
+        parsed.ok_or(DateParseError(old))
+    }
+}
+
+/// A date field that used to be an [`OldDate`] and should now be a [`DateTime<Utc>`]  
+/// The conversion is fallible, go through [`Compatible::deserialize_try_with`],
+/// [`Compatible::try_into_current`] or [`Compatible::deserialize_with_error`]  
+pub type CompatibleDateTime<Current = DateTime<Utc>> = Compatible<OldDate, Current>;
+
+#[test]
+pub fn test_date_time() {
+    use serde::*;
+    #[derive(Debug, Deserialize, Serialize)]
+    pub struct Event {
+        #[serde(deserialize_with = "CompatibleDateTime::<DateTime<Utc>>::deserialize_try_with")]
+        pub at: DateTime<Utc>,
+    }
+
+    let expected = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+    let at = |input: &str| -> Result<DateTime<Utc>, serde_json::Error> {
+        serde_json::from_str::<Event>(&format!(r#"{{"at":{input}}}"#)).map(|e| e.at)
+    };
+    assert_eq!(at("1700000000").unwrap(), expected);
+    assert_eq!(
+        at(r#""Tue, 14 Nov 2023 22:13:20 +0000""#).unwrap(),
+        expected
+    );
+    assert_eq!(at(r#""2023-11-15T00:13:20+02:00""#).unwrap(), expected);
+
+    let current = serde_json::to_string(&Event { at: expected }).unwrap();
+    assert_eq!(current, r#"{"at":"2023-11-14T22:13:20Z"}"#);
+    assert_eq!(
+        serde_json::from_str::<Event>(&current).unwrap().at,
+        expected
+    );
+
+    assert!(at(r#""yesterday""#).is_err());
+    assert!(at(&i64::MAX.to_string()).is_err());
+}
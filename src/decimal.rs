@@ -0,0 +1,55 @@
+//! Amounts that used to be stored as `f64` and are now [`Decimal`]s, enabled with the `decimal`
+//! feature  
+//! `rust_decimal` implements `TryFrom<f64>`, so the fallible
+//! [`TryCompatibleWith`](crate::TryCompatibleWith) impl comes for free  
+use super::Compatible;
+use rust_decimal::Decimal;
+
+/// An amount that used to be an `f64` and should now be a [`Decimal`]  
+/// NaN, the infinities and values outside the decimal range can't convert, go through
+/// [`Compatible::deserialize_try_with`], [`Compatible::try_into_current`] or
+/// [`Compatible::deserialize_with_error`] so they are reported instead of guessed at  
+/// Current values are read from `Decimal`'s string form such as `"19.99"`  
+pub type CompatibleDecimal<Current = Decimal> = Compatible<f64, Current>;
+
+#[test]
+pub fn test_decimal() {
+    use crate::CompatibleError;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Deserialize, Serialize)]
+    pub struct Invoice {
+        #[serde(deserialize_with = "CompatibleDecimal::<Decimal>::deserialize_try_with")]
+        pub total: Decimal,
+    }
+
+    let total = |input: &str| -> Result<Decimal, serde_json::Error> {
+        serde_json::from_str::<Invoice>(&format!(r#"{{"total":{input}}}"#)).map(|i| i.total)
+    };
+    assert_eq!(total("19.99").unwrap(), Decimal::new(1999, 2));
+    assert_eq!(total("0.1").unwrap().to_string(), "0.1");
+    assert_eq!(total(r#""-0.050""#).unwrap().to_string(), "-0.050");
+    assert_eq!(
+        serde_json::to_string(&Invoice {
+            total: Decimal::new(1999, 2)
+        })
+        .unwrap(),
+        r#"{"total":"19.99"}"#
+    );
+    assert!(total("1e40").is_err());
+
+    let parse = |input: &str| {
+        <CompatibleDecimal>::deserialize_with_error(&mut serde_json::Deserializer::from_str(input))
+    };
+    assert!(matches!(parse("1e40"), Err(CompatibleError::Conversion(_))));
+    assert!(matches!(
+        parse(r#""1.2.3""#),
+        Err(CompatibleError::NeitherVariant(_))
+    ));
+    assert!(<CompatibleDecimal>::from_old(f64::NAN)
+        .try_into_current()
+        .is_err());
+    assert!(<CompatibleDecimal>::from_old(f64::INFINITY)
+        .try_into_current()
+        .is_err());
+}
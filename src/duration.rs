@@ -0,0 +1,33 @@
+use super::Compatible;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// A span in whole milliseconds, the usual shape of legacy timeout and interval fields  
+#[derive(Serialize, Deserialize, PartialEq, PartialOrd, Ord, Eq, Debug, Hash, Clone, Copy)]
+#[serde(transparent)]
+pub struct Millis(pub u64);
+
+impl From<Millis> for Duration {
+    fn from(old: Millis) -> Self {
+        Duration::from_millis(old.0)
+    }
+}
+
+/// A duration field that used to be stored as [`Millis`]  
+/// The current shape is serde's `{"secs": .., "nanos": ..}` for `Duration`  
+pub type CompatibleDuration<Current = Duration> = Compatible<Millis, Current>;
+
+#[test]
+pub fn test_duration() {
+    #[derive(Deserialize, Serialize)]
+    pub struct Retry {
+        pub delay: CompatibleDuration,
+    }
+
+    let old: Retry = serde_json::from_str(r#"{"delay":1500}"#).unwrap();
+    let current = serde_json::to_string(&old).unwrap();
+    assert_eq!(current, r#"{"delay":{"secs":1,"nanos":500000000}}"#);
+
+    let current: Retry = serde_json::from_str(&current).unwrap();
+    assert_eq!(current.delay.into_current(), Duration::from_millis(1500));
+}
@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+
+/// Marks an old tuple or array whose elements each convert into the matching current element  
+/// `std` types can't get new `From` impls, so the old side is wrapped in this instead  
+/// ```rust,ignore
+/// pub struct Point(Compatible<Elementwise<(i32, i32)>, (f64, f64)>);
+/// ```
+#[derive(Serialize, Deserialize, PartialEq, PartialOrd, Ord, Eq, Debug, Hash, Clone, Copy)]
+#[serde(transparent)]
+pub struct Elementwise<T>(pub T);
+
+macro_rules! tuple_from {
+    ($($old:ident => $current:ident),+) => {
+        impl<$($old, $current),+> From<Elementwise<($($old,)+)>> for ($($current,)+)
+        where
+            $($current: From<$old>),+
+        {
+            #[allow(non_snake_case)]
+            fn from(old: Elementwise<($($old,)+)>) -> Self {
+                let ($($old,)+) = old.0;
+                ($($current::from($old),)+)
+            }
+        }
+    };
+}
+
+tuple_from!(A => NA);
+tuple_from!(A => NA, B => NB);
+tuple_from!(A => NA, B => NB, C => NC);
+tuple_from!(A => NA, B => NB, C => NC, D => ND);
+
+impl<Old, Current, const N: usize> From<Elementwise<[Old; N]>> for [Current; N]
+where
+    Current: From<Old>,
+{
+    fn from(old: Elementwise<[Old; N]>) -> Self {
+        old.0.map(Current::from)
+    }
+}
+
+#[test]
+pub fn test_elementwise() {
+    use crate::Compatible;
+
+    let pair: Compatible<Elementwise<(i32, u8)>, (i64, u32)> =
+        serde_json::from_str("[1,2]").unwrap();
+    assert_eq!(pair.into_current(), (1i64, 2u32));
+
+    type OldQuad = Elementwise<(u8, u8, u8, u8)>;
+    let quad: Compatible<OldQuad, (u16, u32, u64, i64)> =
+        Compatible::from_old(Elementwise((1, 2, 3, 4)));
+    assert_eq!(quad.into_current(), (1, 2, 3, 4));
+
+    let array: Compatible<Elementwise<[u8; 3]>, [u32; 3]> =
+        serde_json::from_str("[1,2,3]").unwrap();
+    assert_eq!(array.into_current(), [1u32, 2, 3]);
+}
@@ -0,0 +1,40 @@
+//! Type erased serialization, enabled with the `erased-serde` feature  
+use super::{Alt, Compatible};
+use serde::Serialize;
+
+impl<Old, Current> Compatible<Old, Current>
+where
+    Old: Serialize,
+    Current: Serialize,
+{
+    /// The held value as an [`erased_serde::Serialize`] trait object, in whichever shape it is,
+    /// so values of different types can share a collection and be serialized later  
+    pub fn as_serialize(&self) -> &dyn erased_serde::Serialize {
+        match self.0 {
+            Alt::Old(ref old) => old,
+            Alt::Current(ref current) => current,
+        }
+    }
+}
+
+#[test]
+pub fn test_as_serialize() {
+    let text: Compatible<String, Vec<u8>> = Compatible::from_old("ab".into());
+    let bytes: Compatible<String, Vec<u8>> = Compatible::from_current(vec![1, 2]);
+    let flag: Compatible<bool, u8> = Compatible::from_current(1);
+    let pending: Vec<&dyn erased_serde::Serialize> = vec![
+        text.as_serialize(),
+        bytes.as_serialize(),
+        flag.as_serialize(),
+    ];
+
+    assert_eq!(
+        serde_json::to_string(&pending).unwrap(),
+        r#"["ab",[1,2],1]"#
+    );
+
+    let mut out = Vec::new();
+    let mut serializer = serde_json::Serializer::new(&mut out);
+    erased_serde::serialize(text.as_serialize(), &mut serializer).unwrap();
+    assert_eq!(out, br#""ab""#);
+}
@@ -0,0 +1,111 @@
+use super::{Alt, Compatible, TryCompatibleWith};
+use core::fmt;
+
+/// Returned when a value was expected to be current but is still in the old shape  
+/// The untouched old value is kept so it can be recovered with [`ConversionError::into_old`]  
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Copy)]
+pub struct ConversionError<Old>(Old);
+
+impl<Old> ConversionError<Old> {
+    pub fn new(old: Old) -> Self {
+        ConversionError(old)
+    }
+
+    pub fn old(&self) -> &Old {
+        &self.0
+    }
+
+    pub fn into_old(self) -> Old {
+        self.0
+    }
+}
+
+impl<Old> fmt::Display for ConversionError<Old> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("value is still in the old format and was not converted")
+    }
+}
+
+impl<Old: fmt::Debug> std::error::Error for ConversionError<Old> {}
+
+/// Why [`Compatible::deserialize_with_error`] failed  
+#[derive(Debug)]
+pub enum CompatibleError {
+    /// The input matched neither shape, with the deserializer's message  
+    NeitherVariant(String),
+    /// The input was old data but the fallible conversion rejected it  
+    Conversion(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl fmt::Display for CompatibleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompatibleError::NeitherVariant(message) => {
+                write!(
+                    f,
+                    "input matched neither the old nor the current shape: {message}"
+                )
+            }
+            CompatibleError::Conversion(err) => {
+                write!(f, "old value could not be converted: {err}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CompatibleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CompatibleError::NeitherVariant(_) => None,
+            CompatibleError::Conversion(err) => Some(&**err),
+        }
+    }
+}
+
+impl<Old, Current> Compatible<Old, Current> {
+    /// Deserializes and converts with `TryCompatibleWith`, telling bad data apart from old data
+    /// that couldn't be upgraded  
+    pub fn deserialize_with_error<'de, D>(deserializer: D) -> Result<Current, CompatibleError>
+    where
+        D: serde::de::Deserializer<'de>,
+        Alt<Old, Current>: serde::de::Deserialize<'de>,
+        Current: TryCompatibleWith<Old>,
+        Current::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let alt = <Alt<Old, Current> as serde::de::Deserialize>::deserialize(deserializer)
+            .map_err(|err| CompatibleError::NeitherVariant(err.to_string()))?;
+        Compatible(alt)
+            .try_into_current()
+            .map_err(|err| CompatibleError::Conversion(Box::new(err)))
+    }
+}
+
+#[test]
+pub fn test_deserialize_with_error() {
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    pub struct Port(u16);
+
+    impl TryFrom<String> for Port {
+        type Error = core::num::ParseIntError;
+        fn try_from(old: String) -> Result<Self, Self::Error> {
+            old.parse().map(Port)
+        }
+    }
+
+    let parse = |input: &str| {
+        Compatible::<String, Port>::deserialize_with_error(&mut serde_json::Deserializer::from_str(
+            input,
+        ))
+    };
+
+    assert_eq!(parse(r#""80""#).unwrap(), Port(80));
+    assert_eq!(parse("81").unwrap(), Port(81));
+    assert!(matches!(
+        parse(r#""http""#),
+        Err(CompatibleError::Conversion(_))
+    ));
+    assert!(matches!(
+        parse("[]"),
+        Err(CompatibleError::NeitherVariant(_))
+    ));
+}
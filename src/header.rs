@@ -0,0 +1,144 @@
+use super::{Alt, Compatible, Version};
+use core::fmt;
+use core::marker::PhantomData;
+use serde::de::{Error, SeqAccess, Visitor};
+use serde::ser::SerializeTuple;
+use serde::{Deserialize, Serialize};
+
+impl Version {
+    /// The one byte tag written by [`Compatible::serialize_with_version_header`]  
+    pub fn tag(self) -> u8 {
+        match self {
+            Version::Old => 0,
+            Version::Current => 1,
+        }
+    }
+
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(Version::Old),
+            1 => Some(Version::Current),
+            _ => None,
+        }
+    }
+}
+
+impl<Old, Current> Compatible<Old, Current> {
+    /// Serializes the held variant as a `(tag, body)` tuple where the tag is
+    /// [`Version::tag`]  
+    /// In binary formats such as bincode or postcard this is a single byte followed by the
+    /// body, which reads back unambiguously with
+    /// [`Compatible::deserialize_with_version_header`] where the untagged match can't work  
+    pub fn serialize_with_version_header<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+        Old: Serialize,
+        Current: Serialize,
+    {
+        let mut tuple = serializer.serialize_tuple(2)?;
+        tuple.serialize_element(&self.version().tag())?;
+        match self.0 {
+            Alt::Old(ref old) => tuple.serialize_element(old)?,
+            Alt::Current(ref current) => tuple.serialize_element(current)?,
+        }
+        tuple.end()
+    }
+
+    /// Reads what [`Compatible::serialize_with_version_header`] wrote, the held variant is
+    /// the one named by the tag and isn't converted  
+    pub fn deserialize_with_version_header<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+        Old: Deserialize<'de>,
+        Current: Deserialize<'de>,
+    {
+        deserializer.deserialize_tuple(2, HeaderVisitor(PhantomData))
+    }
+}
+
+struct HeaderVisitor<Old, Current>(PhantomData<fn() -> (Old, Current)>);
+
+impl<'de, Old, Current> Visitor<'de> for HeaderVisitor<Old, Current>
+where
+    Old: Deserialize<'de>,
+    Current: Deserialize<'de>,
+{
+    type Value = Compatible<Old, Current>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a version tag followed by the body")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let tag: u8 = seq
+            .next_element()?
+            .ok_or_else(|| Error::invalid_length(0, &self))?;
+        let value = match Version::from_tag(tag) {
+            Some(Version::Old) => seq.next_element()?.map(Compatible::from_old),
+            Some(Version::Current) => seq.next_element()?.map(Compatible::from_current),
+            None => return Err(Error::custom(format!("unknown version tag {tag}"))),
+        };
+        value.ok_or_else(|| Error::invalid_length(1, &self))
+    }
+}
+
+#[test]
+pub fn test_version_header() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    pub struct Old(u8);
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    pub struct New(u8);
+
+    let roundtrip = |value: Compatible<Old, New>| {
+        let mut bytes = Vec::new();
+        value
+            .serialize_with_version_header(&mut serde_json::Serializer::new(&mut bytes))
+            .unwrap();
+        let read = Compatible::<Old, New>::deserialize_with_version_header(
+            &mut serde_json::Deserializer::from_slice(&bytes),
+        )
+        .unwrap();
+        (String::from_utf8(bytes).unwrap(), read)
+    };
+
+    // Both shapes are the same number, only the tag tells them apart
+    let (bytes, old) = roundtrip(Compatible::from_old(Old(7)));
+    assert_eq!(bytes, "[0,7]");
+    assert_eq!(old.into_result(), Err(Old(7)));
+
+    let (bytes, current) = roundtrip(Compatible::from_current(New(7)));
+    assert_eq!(bytes, "[1,7]");
+    assert_eq!(current.into_result(), Ok(New(7)));
+
+    let err = Compatible::<Old, New>::deserialize_with_version_header(
+        &mut serde_json::Deserializer::from_str("[2,7]"),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("unknown version tag 2"));
+
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct Record(
+        #[serde(
+            serialize_with = "Compatible::serialize_with_version_header",
+            deserialize_with = "Compatible::deserialize_with_version_header"
+        )]
+        Compatible<Old, New>,
+    );
+
+    // postcard writes the tag as the first byte and the body right after it
+    let bytes = postcard::to_allocvec(&Record(Compatible::from_old(Old(7)))).unwrap();
+    assert_eq!(bytes, [Version::Old.tag(), 7]);
+    let old: Record = postcard::from_bytes(&bytes).unwrap();
+    assert_eq!(old.0.into_result(), Err(Old(7)));
+
+    let bytes = postcard::to_allocvec(&Record(Compatible::from_current(New(7)))).unwrap();
+    assert_eq!(bytes, [Version::Current.tag(), 7]);
+    let current: Record = postcard::from_bytes(&bytes).unwrap();
+    assert_eq!(current.0.into_result(), Ok(New(7)));
+
+    assert!(postcard::from_bytes::<Record>(&[2, 7]).is_err());
+}
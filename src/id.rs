@@ -0,0 +1,56 @@
+//! IDs that used to be stored as plain strings and are now parsed [`Uuid`]s, enabled with the
+//! `uuid` feature  
+//! `uuid` implements `TryFrom<String>`, so the fallible
+//! [`TryCompatibleWith`](crate::TryCompatibleWith) impl comes for free and parse failures are
+//! reported instead of panicking  
+use super::Compatible;
+use uuid::Uuid;
+
+/// An ID that used to be any string and should now be a [`Uuid`]  
+/// Both shapes are strings so every input reads as old, go through
+/// [`Compatible::deserialize_try_with`], [`Compatible::try_into_current`] or
+/// [`Compatible::deserialize_with_error`] to catch the ones that don't parse  
+pub type CompatibleUuid<Current = Uuid> = Compatible<String, Current>;
+
+#[test]
+pub fn test_uuid() {
+    use crate::CompatibleError;
+    use serde::{Deserialize, Serialize};
+
+    const ID: &str = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+
+    #[derive(Debug, Deserialize, Serialize)]
+    pub struct User {
+        #[serde(deserialize_with = "CompatibleUuid::<Uuid>::deserialize_try_with")]
+        pub id: Uuid,
+    }
+
+    let user: User = serde_json::from_str(&format!(r#"{{"id":"{ID}"}}"#)).unwrap();
+    assert_eq!(user.id, Uuid::parse_str(ID).unwrap());
+    assert_eq!(
+        serde_json::to_string(&user).unwrap(),
+        format!(r#"{{"id":"{ID}"}}"#)
+    );
+    let simple: User =
+        serde_json::from_str(r#"{"id":"67e5504410b1426f9247bb680e5fe0c8"}"#).unwrap();
+    assert_eq!(simple.id, user.id);
+    let err = serde_json::from_str::<User>(r#"{"id":"user-42"}"#).unwrap_err();
+    assert!(err.to_string().contains("invalid"), "{err}");
+
+    let parse = |input: &str| {
+        <CompatibleUuid>::deserialize_with_error(&mut serde_json::Deserializer::from_str(input))
+    };
+    assert!(matches!(
+        parse(r#""user-42""#),
+        Err(CompatibleError::Conversion(_))
+    ));
+    assert!(matches!(
+        parse("42"),
+        Err(CompatibleError::NeitherVariant(_))
+    ));
+    assert!(
+        <CompatibleUuid>::from_old("67e55044-10b1-426f-9247-bb680e5fe0cz".into())
+            .try_into_current()
+            .is_err()
+    );
+}
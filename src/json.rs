@@ -0,0 +1,739 @@
+//! Helpers for reading and writing `Compatible` data as JSON, enabled with the `json` feature  
+use super::{Alt, Compatible, CompatibleWith, Version};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, Read};
+
+/// Generates a function usable with `#[serde(serialize_with)]` that writes a `Compatible` field
+/// through [`Compatible::transform_serialize`] with the listed key renames  
+/// ```rust,ignore
+/// compatible_rename_keys!(serialize_user, OldUser, User, [("display_name", "displayName")]);
+///
+/// #[derive(Serialize)]
+/// pub struct Export {
+///     #[serde(serialize_with = "serialize_user")]
+///     pub user: Compatible<OldUser, User>,
+/// }
+/// ```
+#[macro_export]
+macro_rules! compatible_rename_keys {
+    ($vis:vis $name:ident, $old:ty, $current:ty, [$(($from:expr, $to:expr)),* $(,)?]) => {
+        $vis fn $name<S>(
+            value: &$crate::Compatible<$old, $current>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error>
+        where
+            S: $crate::__serde::Serializer,
+        {
+            value.transform_serialize(&[$(($from, $to)),*], serializer)
+        }
+    };
+}
+
+/// Reads newline delimited JSON where every line may be in the old or the current shape and
+/// yields the current values  
+/// Blank lines are skipped, a line that matches neither shape yields an `Err` and reading
+/// carries on with the next line  
+pub fn deserialize_ndjson<R, Old, Current>(
+    reader: R,
+) -> impl Iterator<Item = Result<Current, serde_json::Error>>
+where
+    R: BufRead,
+    Old: for<'de> Deserialize<'de>,
+    Current: for<'de> Deserialize<'de> + CompatibleWith<Old>,
+{
+    reader
+        .lines()
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|line| {
+            let line = line.map_err(serde_json::Error::io)?;
+            let compatible: Compatible<Old, Current> = serde_json::from_str(&line)?;
+            Ok(compatible.into_current())
+        })
+}
+
+/// Reads a single JSON value in the old or the current shape from `reader` and returns the
+/// current value, the `Compatible` counterpart of `serde_json::from_reader`  
+pub fn from_json_reader<R, Old, Current>(reader: R) -> Result<Current, serde_json::Error>
+where
+    R: Read,
+    Old: for<'de> Deserialize<'de>,
+    Current: for<'de> Deserialize<'de> + CompatibleWith<Old>,
+{
+    let compatible: Compatible<Old, Current> = serde_json::from_reader(reader)?;
+    Ok(compatible.into_current())
+}
+
+/// A field that used to hold a freeform JSON object and now has a schema  
+/// Any object matches the old `Map`, so the plain `Deserialize` would send current data through
+/// `From<Map>` as well, deserialize these fields with
+/// `#[serde(deserialize_with = "CompatibleMap::<Current>::deserialize_current_first")]` to try
+/// the typed shape first  
+pub type CompatibleMap<Current> = Compatible<Map<String, Value>, Current>;
+
+type Migration = Box<dyn Fn(Value) -> Result<Value, serde_json::Error> + Send + Sync>;
+
+/// Classifies a JSON document like [`Compatible::probe_json`] but against lightweight stand-ins
+/// for the two shapes  
+/// A probe only declares the fields that tell the shapes apart, typed as
+/// [`IgnoredAny`](serde::de::IgnoredAny) where the value doesn't matter, every other key is
+/// skipped without allocating  
+pub fn probe_json_by<'a, OldProbe, CurrentProbe>(
+    bytes: &'a [u8],
+) -> Result<Version, serde_json::Error>
+where
+    OldProbe: Deserialize<'a>,
+    CurrentProbe: Deserialize<'a>,
+{
+    Compatible::<OldProbe, CurrentProbe>::probe_json(bytes)
+}
+
+/// Migrations for many `(Old, Current)` pairs looked up by name at runtime  
+/// Each one deserializes a [`Value`] through `Compatible<Old, Current>` and returns the current
+/// shape as a `Value` again  
+/// The returned object's keys are sorted unless the `preserve-order` feature is enabled  
+#[derive(Default)]
+pub struct MigrationRegistry {
+    migrations: HashMap<String, Migration>,
+}
+
+impl MigrationRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the `Old` to `Current` migration under `name`, replacing any previous one  
+    pub fn register<Old, Current>(&mut self, name: impl Into<String>) -> &mut Self
+    where
+        Old: for<'de> Deserialize<'de> + 'static,
+        Current: for<'de> Deserialize<'de> + Serialize + CompatibleWith<Old> + 'static,
+    {
+        self.migrations.insert(
+            name.into(),
+            Box::new(|value| {
+                let compatible: Compatible<Old, Current> = serde_json::from_value(value)?;
+                serde_json::to_value(compatible.into_current())
+            }),
+        );
+        self
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.migrations.contains_key(name)
+    }
+
+    /// Runs the migration registered under `name`  
+    pub fn migrate(&self, name: &str, value: Value) -> Result<Value, serde_json::Error> {
+        let migration = self.migrations.get(name).ok_or_else(|| {
+            serde::de::Error::custom(format_args!("no migration registered for `{name}`"))
+        })?;
+        migration(value)
+    }
+}
+
+/// Paths that differ between the old and the converted current JSON, nested object keys are
+/// joined with `.` and each list is sorted  
+#[derive(PartialEq, Eq, Debug, Default, Clone)]
+pub struct Changes {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    fn collect(&mut self, path: &str, old: &Value, current: &Value) {
+        let key = |k: &String| match path {
+            "" => k.clone(),
+            path => format!("{path}.{k}"),
+        };
+        match (old, current) {
+            (Value::Object(old), Value::Object(current)) => {
+                for (k, old_value) in old {
+                    match current.get(k) {
+                        Some(current_value) => self.collect(&key(k), old_value, current_value),
+                        None => self.removed.push(key(k)),
+                    }
+                }
+                self.added
+                    .extend(current.keys().filter(|k| !old.contains_key(*k)).map(key));
+            }
+            (old, current) if old != current => self.changed.push(path.to_string()),
+            _ => {}
+        }
+    }
+}
+
+impl<Old, Current> Compatible<Old, Current> {
+    /// When the old variant is held, converts a copy and reports what the conversion changed
+    /// in the JSON representation, `None` for values that are already current  
+    pub fn diff_against_old(&self) -> Result<Option<Changes>, serde_json::Error>
+    where
+        Old: Serialize + Clone,
+        Current: Serialize + CompatibleWith<Old>,
+    {
+        let Alt::Old(ref old) = self.0 else {
+            return Ok(None);
+        };
+        let before = serde_json::to_value(old)?;
+        let after = serde_json::to_value(super::upgrade::<_, Current>(old.clone()))?;
+        let mut changes = Changes::default();
+        changes.collect("", &before, &after);
+        changes.added.sort();
+        changes.removed.sort();
+        changes.changed.sort();
+        Ok(Some(changes))
+    }
+
+    /// Converts and writes the current value as a JSON string  
+    pub fn into_current_json(self) -> Result<String, serde_json::Error>
+    where
+        Current: Serialize + CompatibleWith<Old>,
+    {
+        serde_json::to_string(&self.into_current())
+    }
+
+    /// Pretty-printed JSON in the current shape, for migrated files meant for human review  
+    /// An old value is cloned and converted like [`Compatible::as_current`] does  
+    pub fn to_json_pretty(&self) -> Result<String, serde_json::Error>
+    where
+        Old: Clone,
+        Current: Serialize + CompatibleWith<Old>,
+    {
+        serde_json::to_string_pretty(&self.as_current())
+    }
+
+    /// Serializes the current shape with its top-level keys renamed by the `(from, to)` pairs in
+    /// `renames`, for consumers that expect other names than the current type uses  
+    /// Keys are sorted unless the `preserve-order` feature is enabled, which keeps the current
+    /// type's field order with each renamed key in its original place  
+    /// Renaming a key onto one that is already there, and isn't renamed away itself, is an error  
+    /// It goes through a [`Value`], so it works with any serializer, use
+    /// [`compatible_rename_keys!`](crate::compatible_rename_keys) to get a function for
+    /// `#[serde(serialize_with = "...")]`  
+    pub fn transform_serialize<S>(
+        &self,
+        renames: &[(&str, &str)],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+        Old: Clone,
+        Current: Serialize + CompatibleWith<Old>,
+    {
+        let value = match serde_json::to_value(self.as_current()) {
+            // Rebuild the map so renamed keys keep their position and swapped names don't
+            // overwrite each other
+            Ok(Value::Object(map)) => {
+                let mut renamed = Map::new();
+                for (key, field) in map {
+                    let key = match renames.iter().find(|&&(from, _)| from == key) {
+                        Some(&(_, to)) => to.to_owned(),
+                        None => key,
+                    };
+                    if renamed.contains_key(&key) {
+                        return Err(serde::ser::Error::custom(format_args!(
+                            "renaming keys produced `{key}` more than once"
+                        )));
+                    }
+                    renamed.insert(key, field);
+                }
+                Value::Object(renamed)
+            }
+            value => value.map_err(serde::ser::Error::custom)?,
+        };
+        value.serialize(serializer)
+    }
+
+    /// Parses `bytes` as whichever shape matches and errors if both do, as a guard for
+    /// `Current` types that are untagged themselves and can overlap the old shape  
+    /// Ambiguous data has to be read with an explicit version through
+    /// [`Compatible::deserialize_version`]  
+    pub fn from_json_unambiguous<'a>(bytes: &'a [u8]) -> Result<Self, serde_json::Error>
+    where
+        Old: Deserialize<'a>,
+        Current: Deserialize<'a>,
+    {
+        match (
+            serde_json::from_slice::<Old>(bytes),
+            serde_json::from_slice::<Current>(bytes),
+        ) {
+            (Ok(_), Ok(_)) => Err(serde::de::Error::custom(
+                "input matches both the old and the current shape, read it with an explicit version",
+            )),
+            (Ok(old), Err(_)) => Ok(Compatible::from_old(old)),
+            (Err(_), current) => current.map(Compatible::from_current),
+        }
+    }
+
+    /// Classifies a JSON document as old or current without keeping or converting either value  
+    /// The old shape is tried first like the untagged match does, each attempt parses straight
+    /// from `bytes` without buffering, if neither matches the error from `Current` is returned  
+    /// Both attempts build the full value, so for large payloads route with [`probe_json_by`]
+    /// and lightweight probe types instead  
+    pub fn probe_json<'a>(bytes: &'a [u8]) -> Result<Version, serde_json::Error>
+    where
+        Old: Deserialize<'a>,
+        Current: Deserialize<'a>,
+    {
+        if serde_json::from_slice::<Old>(bytes).is_ok() {
+            return Ok(Version::Old);
+        }
+        serde_json::from_slice::<Current>(bytes).map(|_| Version::Current)
+    }
+}
+
+#[test]
+pub fn test_ndjson() {
+    use serde::*;
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Old {
+        pub a: i32,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    pub struct New {
+        pub a: String,
+        pub b: i32,
+    }
+
+    impl From<Old> for New {
+        fn from(old: Old) -> Self {
+            New {
+                a: old.a.to_string(),
+                b: 0,
+            }
+        }
+    }
+
+    let input = b"{\"a\":1}\n{\"a\":\"2\",\"b\":3}\n\n{\"a\":\n";
+    let records = deserialize_ndjson::<_, Old, New>(&input[..]).collect::<Vec<_>>();
+
+    assert_eq!(records.len(), 3);
+    assert_eq!(
+        records[0].as_ref().unwrap(),
+        &New {
+            a: "1".into(),
+            b: 0
+        }
+    );
+    assert_eq!(
+        records[1].as_ref().unwrap(),
+        &New {
+            a: "2".into(),
+            b: 3
+        }
+    );
+    assert!(records[2].is_err());
+}
+
+#[test]
+pub fn test_registry() {
+    use serde::*;
+    use serde_json::json;
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct OldUser {
+        pub name: String,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct User {
+        pub name: String,
+        pub admin: bool,
+    }
+
+    impl From<OldUser> for User {
+        fn from(old: OldUser) -> Self {
+            User {
+                name: old.name,
+                admin: false,
+            }
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Port(u16);
+
+    impl From<String> for Port {
+        fn from(old: String) -> Self {
+            Port(old.parse().unwrap_or(80))
+        }
+    }
+
+    let mut registry = MigrationRegistry::new();
+    registry
+        .register::<OldUser, User>("user")
+        .register::<String, Port>("port");
+
+    assert_eq!(
+        registry.migrate("user", json!({"name": "a"})).unwrap(),
+        json!({"name": "a", "admin": false})
+    );
+    assert_eq!(
+        registry
+            .migrate("user", json!({"name": "b", "admin": true}))
+            .unwrap(),
+        json!({"name": "b", "admin": true})
+    );
+    assert_eq!(
+        registry.migrate("port", json!("8080")).unwrap(),
+        json!(8080)
+    );
+    assert!(registry.migrate("group", json!({})).is_err());
+}
+
+#[test]
+pub fn test_probe_json() {
+    use serde::*;
+    #[derive(Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Old {
+        pub a: i32,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct New {
+        pub a: String,
+        pub b: Vec<i32>,
+    }
+
+    type Probe = Compatible<Old, New>;
+    assert_eq!(Probe::probe_json(br#"{"a":1}"#).unwrap(), Version::Old);
+    assert_eq!(
+        Probe::probe_json(br#"{"a":"1","b":[1,2,3]}"#).unwrap(),
+        Version::Current
+    );
+    assert!(Probe::probe_json(br#"{"c":1}"#).is_err());
+}
+
+#[test]
+pub fn test_probe_json_by() {
+    use serde::de::IgnoredAny;
+
+    /// Old payloads had a numeric `a` and nothing else
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct OldProbe {
+        #[serde(rename = "a")]
+        pub _a: u64,
+    }
+
+    /// Only the presence of `b` marks a current payload, its contents are skipped
+    #[derive(Deserialize)]
+    pub struct CurrentProbe {
+        #[serde(rename = "b")]
+        pub _b: IgnoredAny,
+    }
+
+    let probe = probe_json_by::<OldProbe, CurrentProbe>;
+    assert_eq!(probe(br#"{"a":1}"#).unwrap(), Version::Old);
+    assert_eq!(
+        probe(br#"{"a":"1","b":[[1,2],{"deep":[3]}]}"#).unwrap(),
+        Version::Current
+    );
+    assert!(probe(br#"{"a":"1"}"#).is_err());
+}
+
+#[test]
+pub fn test_from_json_reader() {
+    use std::io::Cursor;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    pub struct Version(u32);
+
+    impl From<String> for Version {
+        fn from(old: String) -> Self {
+            Version(old.trim_start_matches('v').parse().unwrap_or_default())
+        }
+    }
+
+    let old = from_json_reader::<_, String, Version>(Cursor::new(r#" "v3" "#)).unwrap();
+    assert_eq!(old, Version(3));
+
+    let current = from_json_reader::<_, String, Version>(Cursor::new("4")).unwrap();
+    assert_eq!(current, Version(4));
+
+    assert!(from_json_reader::<_, String, Version>(Cursor::new("[]")).is_err());
+}
+
+#[test]
+pub fn test_diff_against_old() {
+    use serde::*;
+    #[derive(Clone, Serialize)]
+    pub struct Old {
+        pub name: String,
+        pub port: u16,
+        pub legacy: bool,
+        pub tls: OldTls,
+    }
+
+    #[derive(Clone, Serialize)]
+    pub struct OldTls {
+        pub cert: String,
+    }
+
+    #[derive(Serialize)]
+    pub struct New {
+        pub name: String,
+        pub port: u32,
+        pub hosts: Vec<String>,
+        pub tls: NewTls,
+    }
+
+    #[derive(Serialize)]
+    pub struct NewTls {
+        pub cert: String,
+        pub key: String,
+    }
+
+    impl From<Old> for New {
+        fn from(old: Old) -> Self {
+            New {
+                name: old.name.to_uppercase(),
+                port: old.port.into(),
+                hosts: vec![],
+                tls: NewTls {
+                    cert: old.tls.cert,
+                    key: String::new(),
+                },
+            }
+        }
+    }
+
+    let old = Compatible::<Old, New>::from_old(Old {
+        name: "web".into(),
+        port: 80,
+        legacy: true,
+        tls: OldTls {
+            cert: "a.pem".into(),
+        },
+    });
+    assert_eq!(
+        old.diff_against_old().unwrap(),
+        Some(Changes {
+            added: vec!["hosts".into(), "tls.key".into()],
+            removed: vec!["legacy".into()],
+            changed: vec!["name".into()],
+        })
+    );
+
+    let current = Compatible::<Old, New>::from_current(old.into_current());
+    assert_eq!(current.diff_against_old().unwrap(), None);
+}
+
+#[test]
+pub fn test_to_json_pretty() {
+    use serde::*;
+    #[derive(Clone)]
+    pub struct Old(u16);
+
+    #[derive(Serialize)]
+    pub struct New {
+        pub port: u16,
+        pub tls: bool,
+    }
+
+    impl From<Old> for New {
+        fn from(old: Old) -> Self {
+            New {
+                port: old.0,
+                tls: false,
+            }
+        }
+    }
+
+    let old = Compatible::<Old, New>::from_old(Old(80));
+    assert_eq!(
+        old.to_json_pretty().unwrap(),
+        "{\n  \"port\": 80,\n  \"tls\": false\n}"
+    );
+}
+
+#[test]
+pub fn test_compatible_map() {
+    use serde::*;
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    pub struct Limits {
+        pub cpu: u32,
+        pub memory: u64,
+    }
+
+    impl From<Map<String, Value>> for Limits {
+        fn from(old: Map<String, Value>) -> Self {
+            let number = |key: &str| old.get(key).and_then(Value::as_u64).unwrap_or_default();
+            Limits {
+                cpu: number("cpu") as u32,
+                memory: number("mem") * 1024,
+            }
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Job {
+        #[serde(deserialize_with = "CompatibleMap::<Limits>::deserialize_current_first")]
+        pub limits: Limits,
+    }
+
+    let old: Job = serde_json::from_str(r#"{"limits":{"cpu":2,"mem":4,"note":"x"}}"#).unwrap();
+    assert_eq!(
+        old.limits,
+        Limits {
+            cpu: 2,
+            memory: 4096
+        }
+    );
+
+    let current: Job = serde_json::from_str(r#"{"limits":{"cpu":2,"memory":4}}"#).unwrap();
+    assert_eq!(current.limits, Limits { cpu: 2, memory: 4 });
+}
+
+#[test]
+pub fn test_from_json_unambiguous() {
+    use serde::*;
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(untagged)]
+    pub enum Limit {
+        Count(u32),
+        Named(String),
+    }
+
+    impl From<u32> for Limit {
+        fn from(old: u32) -> Self {
+            Limit::Named(old.to_string())
+        }
+    }
+
+    let err = Compatible::<u32, Limit>::from_json_unambiguous(b"5").unwrap_err();
+    assert!(err.to_string().contains("matches both"));
+
+    let current = Compatible::<u32, Limit>::from_json_unambiguous(br#""all""#).unwrap();
+    assert_eq!(current.into_result(), Ok(Limit::Named("all".into())));
+
+    let known = Compatible::<u32, Limit>::deserialize_version(
+        Version::Current,
+        &mut serde_json::Deserializer::from_slice(b"5"),
+    )
+    .unwrap();
+    assert_eq!(known.into_result(), Ok(Limit::Count(5)));
+}
+
+#[test]
+pub fn test_into_current_json() {
+    let migrate = |input: &str| {
+        serde_json::from_str::<Compatible<String, Vec<u8>>>(input)
+            .and_then(Compatible::into_current_json)
+    };
+    assert_eq!(migrate(r#""hi""#).unwrap(), "[104,105]");
+    assert_eq!(migrate("[1]").unwrap(), "[1]");
+}
+
+#[test]
+pub fn test_transform_serialize() {
+    #[derive(Clone, Deserialize)]
+    pub struct OldUser {
+        pub name: String,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct User {
+        pub display_name: String,
+        pub id: u32,
+    }
+
+    impl From<OldUser> for User {
+        fn from(old: OldUser) -> Self {
+            User {
+                display_name: old.name,
+                id: 0,
+            }
+        }
+    }
+
+    compatible_rename_keys!(external, OldUser, User, [("display_name", "displayName")]);
+
+    #[derive(Serialize)]
+    pub struct Export {
+        #[serde(serialize_with = "external")]
+        pub user: Compatible<OldUser, User>,
+    }
+
+    let export = Export {
+        user: Compatible::from_old(OldUser { name: "a".into() }),
+    };
+    assert_eq!(
+        serde_json::to_string(&export).unwrap(),
+        r#"{"user":{"displayName":"a","id":0}}"#
+    );
+
+    let swapped = export
+        .user
+        .transform_serialize(
+            &[("id", "display_name"), ("display_name", "id")],
+            serde_json::value::Serializer,
+        )
+        .unwrap();
+    assert_eq!(swapped, serde_json::json!({ "id": "a", "display_name": 0 }));
+
+    let collision = export
+        .user
+        .transform_serialize(&[("display_name", "id")], serde_json::value::Serializer)
+        .unwrap_err();
+    assert_eq!(
+        collision.to_string(),
+        "renaming keys produced `id` more than once"
+    );
+}
+
+#[cfg(feature = "preserve-order")]
+#[test]
+pub fn test_preserve_order() {
+    use serde::*;
+    #[derive(Clone, Deserialize)]
+    pub struct OldUser {
+        pub name: String,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct User {
+        pub name: String,
+        pub admin: bool,
+        pub group: u32,
+    }
+
+    impl From<OldUser> for User {
+        fn from(old: OldUser) -> Self {
+            User {
+                name: old.name,
+                admin: false,
+                group: 0,
+            }
+        }
+    }
+
+    let mut registry = MigrationRegistry::new();
+    registry.register::<OldUser, User>("user");
+    let migrated = registry
+        .migrate("user", serde_json::json!({"name": "a"}))
+        .unwrap();
+    assert_eq!(
+        serde_json::to_string(&migrated).unwrap(),
+        r#"{"name":"a","admin":false,"group":0}"#
+    );
+
+    let user: Compatible<OldUser, User> = Compatible::from_old(OldUser { name: "a".into() });
+    let renamed = user
+        .transform_serialize(&[("name", "userName")], serde_json::value::Serializer)
+        .unwrap();
+    assert_eq!(
+        serde_json::to_string(&renamed).unwrap(),
+        r#"{"userName":"a","admin":false,"group":0}"#
+    );
+}
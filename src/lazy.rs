@@ -0,0 +1,109 @@
+use super::{Alt, Compatible, CompatibleWith};
+use std::cell::{Cell, OnceCell};
+
+/// An old value with a deferred conversion, from [`Compatible::from_old_lazy`]  
+/// The conversion runs on the first read and the result is kept for every read after it  
+pub struct LazyCompatible<Old, Current, F = fn(Old) -> Current> {
+    pending: Cell<Option<(Old, F)>>,
+    current: OnceCell<Current>,
+}
+
+impl<Old, Current> Compatible<Old, Current> {
+    /// Holds `old` and only runs `convert` once the current value is first needed  
+    pub fn from_old_lazy<F>(old: Old, convert: F) -> LazyCompatible<Old, Current, F>
+    where
+        F: FnOnce(Old) -> Current,
+    {
+        LazyCompatible {
+            pending: Cell::new(Some((old, convert))),
+            current: OnceCell::new(),
+        }
+    }
+}
+
+impl<Old, Current> Compatible<Old, Current>
+where
+    Current: CompatibleWith<Old>,
+{
+    /// Moves the value behind a cache, so readers holding `&` can get the current value with
+    /// [`LazyCompatible::get`] and the conversion still only runs once  
+    /// `Compatible` itself has no room for the cache, it stays a bare enum to keep it `Copy`,
+    /// `Hash` and `Ord`  
+    pub fn into_lazy(self) -> LazyCompatible<Old, Current> {
+        match self.0 {
+            Alt::Old(old) => Compatible::from_old_lazy(old, Current::from_old),
+            Alt::Current(current) => LazyCompatible {
+                pending: Cell::new(None),
+                current: OnceCell::from(current),
+            },
+        }
+    }
+}
+
+impl<Old, Current, F> LazyCompatible<Old, Current, F>
+where
+    F: FnOnce(Old) -> Current,
+{
+    /// Converts on the first call, later calls return the same value  
+    pub fn get(&self) -> &Current {
+        self.current.get_or_init(|| {
+            let (old, convert) = self.pending.take().expect("conversion already ran");
+            super::counters::record();
+            convert(old)
+        })
+    }
+
+    pub fn into_current(self) -> Current {
+        self.get();
+        self.current.into_inner().expect("converted above")
+    }
+
+    /// Whether the conversion has run yet  
+    pub fn is_converted(&self) -> bool {
+        self.current.get().is_some()
+    }
+}
+
+#[test]
+pub fn test_from_old_lazy() {
+    let runs = Cell::new(0);
+    let lazy = Compatible::<i32, String>::from_old_lazy(5, |old| {
+        runs.set(runs.get() + 1);
+        old.to_string()
+    });
+    assert!(!lazy.is_converted());
+    assert_eq!(runs.get(), 0);
+
+    assert_eq!(lazy.get(), "5");
+    assert_eq!(lazy.get(), "5");
+    assert_eq!(lazy.into_current(), "5");
+    assert_eq!(runs.get(), 1);
+}
+
+#[test]
+pub fn test_into_lazy() {
+    thread_local! {
+        static RUNS: Cell<u32> = const { Cell::new(0) };
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub struct Label(String);
+
+    impl From<u32> for Label {
+        fn from(old: u32) -> Self {
+            RUNS.with(|runs| runs.set(runs.get() + 1));
+            Label(old.to_string())
+        }
+    }
+
+    let lazy = Compatible::<u32, Label>::from_old(7).into_lazy();
+    let shared = &lazy;
+    assert_eq!(shared.get(), &Label("7".into()));
+    assert_eq!(shared.get(), &Label("7".into()));
+    assert_eq!(RUNS.with(Cell::get), 1);
+
+    let current = Compatible::<u32, Label>::from_current(Label("a".into())).into_lazy();
+    assert!(current.is_converted());
+    assert_eq!(current.into_current(), Label("a".into()));
+    assert_eq!(RUNS.with(Cell::get), 1);
+}
@@ -3,23 +3,111 @@
 //! You just need to provide a `Current: From<Old>` implementation  
 //! And the rest is handled automatically  
 //! Keep in mind that this uses untagged enums so it comes with performance cost  
-pub use compatible_with_derive::CompatibleWith;
+//! Migrated values are serialized straight through the current type's `Serialize` impl, so the
+//! output keys come out in the current struct's field order no matter what order the old input
+//! used  
+//! The helpers in the `json` module that go through a `serde_json::Value`, such as
+//! `MigrationRegistry::migrate` and `Compatible::transform_serialize`, sort object keys
+//! alphabetically instead, enable the `preserve-order` feature to keep the field order there too  
+pub use arena::CompatibleWithArena;
+#[cfg(feature = "base64")]
+pub use bytes::{Base64, CompatibleBytes};
+pub use compatible_with_derive::{compatible_with, CompatibleFields, CompatibleWith, Versioned};
+#[cfg(feature = "counters")]
+pub use counters::{conversion_count, reset_conversion_count};
+pub use date::{CompatibleDate, UnixTimestamp};
+#[cfg(feature = "chrono")]
+pub use datetime::{CompatibleDateTime, DateParseError, OldDate};
+#[cfg(feature = "decimal")]
+pub use decimal::CompatibleDecimal;
+pub use duration::{CompatibleDuration, Millis};
+pub use elementwise::Elementwise;
+pub use error::{CompatibleError, ConversionError};
+#[cfg(feature = "uuid")]
+pub use id::CompatibleUuid;
+pub use lazy::LazyCompatible;
+pub use merge::Merge;
+#[cfg(feature = "indexmap")]
+pub use ordered::{CompatibleIndexMap, CompatibleIndexSet, OrderedEntries};
+#[cfg(feature = "parallel")]
+pub use parallel::par_into_current;
+pub use result::CompatibleResult;
+pub use retain::Retainable;
+pub use seed::{CompatibleSeed, CompatibleWithContext};
 use serde::*;
+pub use strict::StrictCompatible;
+#[cfg(feature = "compact_str")]
+pub use strings::CompatibleCompactString;
+#[cfg(feature = "smol_str")]
+pub use strings::CompatibleSmolStr;
+pub use tree::{convert_tree, DepthLimitExceeded};
+pub use validate::Validate;
+pub use versioned::{Versioned, VersionedCompatible};
 
-/// This is the main type you will be using  
-/// It wraps your old and current type and provides a way to deserialize existing data that might  
-/// match either of the types  
-/// It will deserialize the old type into an deserialize impl for the old type and then convert it  
-/// to the new type  
-/// ```rust,ignore
-/// #[derive(Serialize, Deserialize)]
-/// pub struct Current;
-/// pub struct SomeType {
-///     my_var: Current,
-/// }
-/// ```
+mod arena;
+#[cfg(feature = "base64")]
+mod bytes;
+mod content;
+mod counters;
+mod date;
+#[cfg(feature = "chrono")]
+mod datetime;
+#[cfg(feature = "decimal")]
+mod decimal;
+mod duration;
+mod elementwise;
+#[cfg(feature = "erased-serde")]
+mod erased;
+mod error;
+mod header;
+#[cfg(feature = "uuid")]
+mod id;
+#[cfg(feature = "json")]
+pub mod json;
+mod lazy;
+mod merge;
+#[cfg(feature = "indexmap")]
+mod ordered;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod result;
+mod retain;
+mod seed;
+mod strict;
+#[cfg(any(feature = "compact_str", feature = "smol_str"))]
+mod strings;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+mod tree;
+mod validate;
+mod versioned;
+
+#[doc(hidden)]
+pub use serde as __serde;
+
+/// `use compatible_with::prelude::*;` to bring in the wrapper, the traits, the derive and the
+/// helper macros  
+/// It re-exports every item at the crate root that doesn't need a feature, items behind a
+/// feature such as `json::` or `Base64` are imported from the crate root  
+pub mod prelude {
+    pub use crate::{
+        compatible_any, compatible_default, compatible_version_out, compatible_with,
+        compatible_with_fn,
+    };
+    pub use crate::{convert_tree, serialize_current, serialize_seq_as_current};
+    pub use crate::{Alt, AsCurrent, Compatible, CompatibleFields, CompatibleTo, CompatibleWith};
+    pub use crate::{CompatibleDate, CompatibleDuration, Millis, UnixTimestamp};
+    pub use crate::{CompatibleError, ConversionError, DepthLimitExceeded};
+    pub use crate::{CompatibleResult, LazyCompatible, StrictCompatible, Transparent};
+    pub use crate::{CompatibleSeed, CompatibleWithArena, CompatibleWithContext};
+    pub use crate::{Elementwise, Merge, Retainable, TryCompatibleWith, Validate};
+    pub use crate::{Version, Versioned, VersionedCompatible};
+}
 
-/// The `Current` version of the struct is `CompatibleWith<Old>`
+/// The `Current` version of the struct is `CompatibleWith<Old>`  
+/// A `#[non_exhaustive]` current type from another crate can't be built with a struct literal,
+/// so its `From<Old>` has to live in the defining crate or the conversion has to go through
+/// its public constructors with [`Compatible::deserialize_with_fn`]
 pub trait CompatibleWith<Old> {
     fn from_old(value: Old) -> Self;
 }
@@ -47,9 +135,44 @@ where
     }
 }
 
+/// The fallible version of `CompatibleWith`, for old values that might not convert  
+/// Every `Current: TryFrom<Old>` gets this, including the infallible `From<Old>` ones  
+pub trait TryCompatibleWith<Old>: Sized {
+    type Error;
+    fn try_from_old(value: Old) -> Result<Self, Self::Error>;
+}
+
+impl<Old, Current> TryCompatibleWith<Old> for Current
+where
+    Current: TryFrom<Old>,
+{
+    type Error = Current::Error;
+    fn try_from_old(value: Old) -> Result<Self, Self::Error> {
+        Current::try_from(value)
+    }
+}
+
+/// Every conversion `Compatible` runs goes through here so the `counters` feature sees it  
+fn upgrade<Old, Current: CompatibleWith<Old>>(old: Old) -> Current {
+    counters::record();
+    Current::from_old(old)
+}
+
 mod with {
-    use super::{Compatible, CompatibleWith};
+    use super::{counters, upgrade, Alt, Compatible, CompatibleWith, TryCompatibleWith, Version};
+    use core::cell::Cell;
+    use core::fmt;
+    use core::str::FromStr;
     use serde::Deserialize;
+    use std::borrow::Cow;
+
+    /// `Alt` with the attempt order swapped  
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum CurrentFirst<Old, Current> {
+        Current(Current),
+        Old(Old),
+    }
 
     impl<Old, Current> Compatible<Old, Current> {
         pub fn deserialize_with<'de, D>(deserializer: D) -> Result<Current, D::Error>
@@ -61,39 +184,531 @@ mod with {
             let compatible: Compatible<Old, Current> = Compatible::deserialize(deserializer)?;
             Ok(compatible.into_current())
         }
+
+        /// Like [`Compatible::deserialize_with`] for `Cow<'_, Current>` fields, the migrated value
+        /// is always `Cow::Owned`  
+        pub fn deserialize_cow_with<'de, 'a, D>(
+            deserializer: D,
+        ) -> Result<Cow<'a, Current>, D::Error>
+        where
+            D: serde::de::Deserializer<'de>,
+            Self: Deserialize<'de>,
+            Current: CompatibleWith<Old> + Clone,
+        {
+            Self::deserialize_with(deserializer).map(Cow::Owned)
+        }
+
+        /// Like [`Compatible::deserialize_with`] but converts with `convert` instead of a
+        /// `From<Old>` impl  
+        /// Use [`compatible_with_fn!`](crate::compatible_with_fn) to get a function that can be
+        /// passed to `#[serde(deserialize_with)]`  
+        /// This is also the way in when both shapes are foreign types such as `Vec<Entry>` and
+        /// a `HashMap`, where the orphan rule forbids the `From` impl, the `indexmap` feature
+        /// covers `IndexMap` with `OrderedEntries`  
+        pub fn deserialize_with_fn<'de, D>(
+            deserializer: D,
+            convert: fn(Old) -> Current,
+        ) -> Result<Current, D::Error>
+        where
+            D: serde::de::Deserializer<'de>,
+            Alt<Old, Current>: Deserialize<'de>,
+        {
+            match Alt::deserialize(deserializer)? {
+                Alt::Old(old) => {
+                    counters::record();
+                    Ok(convert(old))
+                }
+                Alt::Current(current) => Ok(current),
+            }
+        }
+
+        /// Like [`Compatible::deserialize_with`] but tries the current shape before the old one  
+        /// Use this when the old shape is permissive enough to also match current data, for
+        /// example an old struct that collects unknown keys with `#[serde(flatten)]`  
+        pub fn deserialize_current_first<'de, D>(deserializer: D) -> Result<Current, D::Error>
+        where
+            D: serde::de::Deserializer<'de>,
+            Old: Deserialize<'de>,
+            Current: Deserialize<'de> + CompatibleWith<Old>,
+        {
+            match CurrentFirst::deserialize(deserializer)? {
+                CurrentFirst::Current(current) => Ok(current),
+                CurrentFirst::Old(old) => Ok(upgrade(old)),
+            }
+        }
+
+        /// Deserialize straight into `Current` without trying the old shape first  
+        /// For data that is known to be current already, old data is rejected with the error
+        /// from `Current`'s own `Deserialize`  
+        pub fn deserialize_assume_current<'de, D>(deserializer: D) -> Result<Current, D::Error>
+        where
+            D: serde::de::Deserializer<'de>,
+            Current: Deserialize<'de>,
+        {
+            Current::deserialize(deserializer)
+        }
+
+        /// Deserialize a string and parse it with `Old`'s `FromStr`, falling back to `Current`'s  
+        /// For flat formats such as CSV where every column is a string and the untagged match
+        /// can't tell the shapes apart, use it as `#[serde(deserialize_with = "...")]` on the
+        /// column  
+        /// The same goes for environment layers of `config`, which keep values as strings unless
+        /// `Environment::try_parsing` is enabled  
+        pub fn deserialize_from_str<'de, D>(deserializer: D) -> Result<Current, D::Error>
+        where
+            D: serde::de::Deserializer<'de>,
+            Old: FromStr,
+            Current: FromStr + CompatibleWith<Old>,
+            Current::Err: fmt::Display,
+        {
+            let value = String::deserialize(deserializer)?;
+            match Old::from_str(&value) {
+                Ok(old) => Ok(upgrade(old)),
+                Err(_) => Current::from_str(&value).map_err(serde::de::Error::custom),
+            }
+        }
+
+        /// Like [`Compatible::deserialize_with`] but also writes the shape that matched into
+        /// `out`  
+        /// Use [`compatible_version_out!`](crate::compatible_version_out) to get a module with a
+        /// function that can be passed to `#[serde(deserialize_with)]`  
+        pub fn deserialize_with_version_out<'de, D>(
+            deserializer: D,
+            out: &Cell<Option<Version>>,
+        ) -> Result<Current, D::Error>
+        where
+            D: serde::de::Deserializer<'de>,
+            Alt<Old, Current>: Deserialize<'de>,
+            Current: CompatibleWith<Old>,
+        {
+            let compatible = Compatible(Alt::deserialize(deserializer)?);
+            out.set(Some(compatible.version()));
+            Ok(compatible.into_current())
+        }
+
+        /// Like [`Compatible::deserialize_with`] but converts with the fallible
+        /// `TryCompatibleWith` impl, a rejected old value becomes a deserialization error  
+        pub fn deserialize_try_with<'de, D>(deserializer: D) -> Result<Current, D::Error>
+        where
+            D: serde::de::Deserializer<'de>,
+            Alt<Old, Current>: Deserialize<'de>,
+            Current: TryCompatibleWith<Old>,
+            Current::Error: fmt::Display,
+        {
+            Compatible(Alt::deserialize(deserializer)?)
+                .try_into_current()
+                .map_err(serde::de::Error::custom)
+        }
+
+        /// Deserialize `Current` but reject anything that matches the old shape, even if it
+        /// could be migrated, for endpoints that have sunset legacy input  
+        pub fn deserialize_current_only<'de, D>(deserializer: D) -> Result<Current, D::Error>
+        where
+            D: serde::de::Deserializer<'de>,
+            Alt<Old, Current>: Deserialize<'de>,
+        {
+            match Alt::deserialize(deserializer)? {
+                Alt::Old(_) => Err(serde::de::Error::custom(
+                    "input is in the old format, which is no longer accepted",
+                )),
+                Alt::Current(current) => Ok(current),
+            }
+        }
+
+        /// Runs the untagged match and converts, but errors unless the input was in the
+        /// `expected` shape, for migration checks that should only ever read one version  
+        /// The check happens before the eager conversion, which [`Compatible::expect_version`]
+        /// can't see past  
+        pub fn deserialize_expect_version<'de, D>(
+            deserializer: D,
+            expected: Version,
+        ) -> Result<Current, D::Error>
+        where
+            D: serde::de::Deserializer<'de>,
+            Alt<Old, Current>: Deserialize<'de>,
+            Current: CompatibleWith<Old>,
+        {
+            let compatible = Compatible(Alt::deserialize(deserializer)?);
+            let version = compatible.version();
+            if version != expected {
+                return Err(serde::de::Error::custom(format_args!(
+                    "expected a value in the {expected:?} shape but found {version:?}"
+                )));
+            }
+            Ok(compatible.into_current())
+        }
+
+        /// Deserialize as the given `version` only, skipping the untagged match  
+        /// Use this when the version is known from somewhere else (a header, a file name) and
+        /// the old shape overlaps one of the current shapes, for example when `Current` is an
+        /// enum and old data looks like one of its variants  
+        pub fn deserialize_version<'de, D>(
+            version: Version,
+            deserializer: D,
+        ) -> Result<Self, D::Error>
+        where
+            D: serde::de::Deserializer<'de>,
+            Old: Deserialize<'de>,
+            Current: Deserialize<'de>,
+        {
+            match version {
+                Version::Old => Old::deserialize(deserializer).map(Compatible::from_old),
+                Version::Current => {
+                    Current::deserialize(deserializer).map(Compatible::from_current)
+                }
+            }
+        }
     }
 }
 
+/// Generates a function usable with `#[serde(deserialize_with)]` that converts old values with
+/// a plain function instead of a `From` impl  
+/// ```rust,ignore
+/// fn parse(old: i32) -> MyType { MyType(old.to_string()) }
+/// compatible_with_fn!(deserialize_my_type, i32, MyType, parse);
+///
+/// #[derive(Deserialize)]
+/// pub struct New {
+///     #[serde(deserialize_with = "deserialize_my_type")]
+///     pub a: MyType,
+/// }
+/// ```
+#[macro_export]
+macro_rules! compatible_with_fn {
+    ($vis:vis $name:ident, $old:ty, $current:ty, $convert:expr) => {
+        $vis fn $name<'de, D>(deserializer: D) -> Result<$current, D::Error>
+        where
+            D: $crate::__serde::Deserializer<'de>,
+        {
+            $crate::Compatible::<$old, $current>::deserialize_with_fn(deserializer, $convert)
+        }
+    };
+}
+
+/// Generates a module whose `deserialize` function is usable with `#[serde(deserialize_with)]`
+/// and records which shape matched, read it back with `last_version()`  
+/// The version is kept in a thread local and overwritten by every call, the types have to be
+/// nameable from the module the macro is invoked in  
+/// ```rust,ignore
+/// compatible_version_out!(my_type_version, i32, MyType);
+///
+/// #[derive(Deserialize)]
+/// pub struct New {
+///     #[serde(deserialize_with = "my_type_version::deserialize")]
+///     pub a: MyType,
+/// }
+///
+/// let new: New = serde_json::from_str(r#"{"a":1}"#)?;
+/// assert_eq!(my_type_version::last_version(), Some(Version::Old));
+/// ```
+#[macro_export]
+macro_rules! compatible_version_out {
+    ($vis:vis $name:ident, $old:ty, $current:ty) => {
+        $vis mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+
+            ::std::thread_local! {
+                static VERSION: ::core::cell::Cell<Option<$crate::Version>> =
+                    const { ::core::cell::Cell::new(None) };
+            }
+
+            pub fn deserialize<'de, D>(deserializer: D) -> Result<$current, D::Error>
+            where
+                D: $crate::__serde::Deserializer<'de>,
+            {
+                VERSION.with(|out| {
+                    $crate::Compatible::<$old, $current>::deserialize_with_version_out(
+                        deserializer,
+                        out,
+                    )
+                })
+            }
+
+            /// The shape matched by the last `deserialize` on this thread
+            pub fn last_version() -> Option<$crate::Version> {
+                VERSION.with(::core::cell::Cell::get)
+            }
+        }
+    };
+}
+
+/// Generates a function usable with `#[serde(default)]` that produces the current value by
+/// converting `Old::default()`  
+/// ```rust,ignore
+/// compatible_default!(default_settings, OldSettings, Settings);
+///
+/// #[derive(Deserialize)]
+/// pub struct Config {
+///     #[serde(default = "default_settings")]
+///     pub settings: Settings,
+/// }
+/// ```
+#[macro_export]
+macro_rules! compatible_default {
+    ($vis:vis $name:ident, $old:ty, $current:ty) => {
+        $vis fn $name() -> $current {
+            <$current as $crate::CompatibleWith<$old>>::from_old(<$old as Default>::default())
+        }
+    };
+}
+
+/// Declares a `Compatible` type alias that accepts several unrelated old shapes for the same
+/// current type, tried in the order they are listed and before the current shape  
+/// Every old type needs a `From<OldX> for Current` impl  
+/// ```rust,ignore
+/// compatible_any! {
+///     pub type AnyPort = Port; u16, String, OldPort
+/// }
+///
+/// let port: AnyPort = serde_json::from_str("\"8080\"")?;
+/// ```
+#[macro_export]
+macro_rules! compatible_any {
+    ($vis:vis type $name:ident = $current:ty; $first:ty, $($rest:ty),+ $(,)?) => {
+        $vis type $name = $crate::Compatible<$crate::compatible_any!(@alt $first, $($rest),+), $current>;
+
+        impl From<$crate::compatible_any!(@alt $first, $($rest),+)> for $current {
+            fn from(value: $crate::compatible_any!(@alt $first, $($rest),+)) -> Self {
+                $crate::compatible_any!(@from $current; value; $first, $($rest),+)
+            }
+        }
+    };
+    (@alt $last:ty) => { $last };
+    (@alt $first:ty, $($rest:ty),+) => {
+        $crate::Alt<$first, $crate::compatible_any!(@alt $($rest),+)>
+    };
+    (@from $current:ty; $value:expr; $last:ty) => {
+        <$current as From<$last>>::from($value)
+    };
+    (@from $current:ty; $value:expr; $first:ty, $($rest:ty),+) => {
+        match $value {
+            $crate::Alt::Old(old) => <$current as From<$first>>::from(old),
+            $crate::Alt::Current(rest) => $crate::compatible_any!(@from $current; rest; $($rest),+),
+        }
+    };
+}
+
+/// This is the main type you will be using  
+/// It wraps your old and current type and provides a way to deserialize existing data that might  
+/// match either of the types  
+/// It will deserialize the old type into an deserialize impl for the old type and then convert it  
+/// to the new type  
+/// The old shape is always tried first, so anything that matches both shapes is treated as old,
+/// for example an enum that used to be stored as its integer discriminant and is now written by
+/// name is fine, but a current enum that also accepts integers would never be read as current  
+/// ```rust,ignore
+/// #[derive(Serialize, Deserialize)]
+/// pub struct Current;
+/// pub struct SomeType {
+///     my_var: Current,
+/// }
+/// ```
 #[derive(PartialEq, PartialOrd, Ord, Eq, Debug, Hash, Clone, Copy)]
 pub struct Compatible<Old, Current>(Alt<Old, Current>);
 
+/// A `Compatible` where the old and current types are the same  
+/// Since `From<T> for T` is the identity conversion, migrating is just a move and the value is
+/// passed through unchanged  
+/// The plain `Deserialize` still buffers the input for the untagged match and runs that
+/// identity conversion, which the `counters` feature counts, read fields with
+/// [`Compatible::deserialize_transparent`] and unwrap with [`Compatible::into_inner`] to skip
+/// both  
+pub type Transparent<T> = Compatible<T, T>;
+
+impl<T> Compatible<T, T> {
+    /// Deserializes `T` directly without the untagged match or a conversion  
+    /// Usable as `#[serde(deserialize_with = "Transparent::<T>::deserialize_transparent")]`  
+    pub fn deserialize_transparent<'de, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+        T: Deserialize<'de>,
+    {
+        T::deserialize(deserializer)
+    }
+
+    /// Takes the value out of either variant, both already hold a `T`  
+    pub fn into_inner(self) -> T {
+        match self.0 {
+            Alt::Old(value) | Alt::Current(value) => value,
+        }
+    }
+}
+
 impl<Old, Current> Compatible<Old, Current>
 where
     Current: CompatibleWith<Old>,
 {
     pub fn into_current(self) -> Current {
         match self.0 {
-            Alt::Old(old) => old.into_current(),
+            Alt::Old(old) => upgrade(old),
             Alt::Current(current) => current,
         }
     }
 
     pub fn make_current(mut self) -> Self {
         if let Alt::Old(old) = self.0 {
-            self.0 = Alt::Current(old.into_current())
+            self.0 = Alt::Current(upgrade(old))
         };
         self
     }
+
+    /// Swaps the `Old` type parameter once the value is current, converting first if it isn't  
+    /// Normalized values with different old types can then share one `Vec`  
+    pub fn retag<NewOld>(self) -> Compatible<NewOld, Current> {
+        Compatible::from_current(self.into_current())
+    }
+
+    /// Converts like [`Compatible::into_current`] but panics if the held value wasn't in the
+    /// `expected` shape, for migration checks that should only ever read one version  
+    /// Deserializing converts eagerly, so a deserialized value always reports
+    /// [`Version::Current`], check input with [`Compatible::deserialize_expect_version`]
+    /// instead and keep this for values built with [`Compatible::from_old`]  
+    pub fn expect_version(self, expected: Version) -> Current {
+        let version = self.version();
+        assert_eq!(
+            version, expected,
+            "expected a value in the {expected:?} shape but found {version:?}"
+        );
+        self.into_current()
+    }
+
+    /// Converts a fixed size array element by element without going through a `Vec`  
+    pub fn normalize_all<const N: usize>(values: [Self; N]) -> [Current; N] {
+        values.map(Self::into_current)
+    }
+
+    /// Converts and boxes the result as a trait object such as `Box<dyn Shape>`  
+    /// `Current` can be the boxed trait object itself, with an `impl From<Old> for Box<dyn Shape>`
+    /// that picks the concrete type, or a concrete type with an
+    /// `impl From<Current> for Box<dyn Shape>`, both are allowed for a local trait since `Box` is
+    /// `#[fundamental]`  
+    pub fn into_current_boxed<T: ?Sized>(self) -> Box<T>
+    where
+        Current: Into<Box<T>>,
+    {
+        self.into_current().into()
+    }
+
+    /// Clones whichever variant is held and converts it, for when only a borrow is available  
+    pub fn cloned_current(&self) -> Current
+    where
+        Old: Clone,
+        Current: Clone,
+    {
+        match self.0 {
+            Alt::Old(ref old) => upgrade(old.clone()),
+            Alt::Current(ref current) => current.clone(),
+        }
+    }
+
+    /// Same as [`Compatible::make_current`] but through a `&mut`, for wrappers stored inside
+    /// something else  
+    /// `Current::default()` is left in place while the conversion runs  
+    pub fn normalize(&mut self)
+    where
+        Current: Default,
+    {
+        self.current_mut();
+    }
+
+    /// Converts an old value in place and reports whether it did, so repeated backfills over a
+    /// partially migrated store only touch what is left  
+    pub fn upgrade(&mut self) -> bool
+    where
+        Current: Default,
+    {
+        let was_old = self.is_old();
+        self.current_mut();
+        was_old
+    }
+
+    /// Normalizes in place and runs `f` against a borrow of the current value  
+    pub fn with_current<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&Current) -> R,
+        Current: Default,
+    {
+        f(self.current_mut())
+    }
+
+    /// Move the converted value out and leave `Current::default()` behind, like `mem::take`  
+    pub fn take(&mut self) -> Current
+    where
+        Current: Default,
+    {
+        core::mem::take(self.current_mut())
+    }
+
+    /// Converts in place, the old value is moved out by leaving `Current::default()` behind
+    /// while the conversion runs  
+    fn current_mut(&mut self) -> &mut Current
+    where
+        Current: Default,
+    {
+        if let Alt::Old(_) = self.0 {
+            if let Alt::Old(old) = core::mem::replace(&mut self.0, Alt::Current(Current::default()))
+            {
+                self.0 = Alt::Current(upgrade(old));
+            }
+        }
+        match self.0 {
+            Alt::Current(ref mut current) => current,
+            Alt::Old(_) => unreachable!("converted above"),
+        }
+    }
 }
 
-#[derive(Deserialize)]
-#[serde(untagged)]
+/// Deserializes like a `#[serde(untagged)]` enum, `Old` is tried first  
 #[derive(PartialEq, PartialOrd, Ord, Eq, Debug, Hash, Clone, Copy)]
 pub enum Alt<Old, Current> {
     Old(Old),
     Current(Current),
 }
 
+impl<'de, Old, Current> serde::de::Deserialize<'de> for Alt<Old, Current>
+where
+    Old: serde::de::Deserialize<'de>,
+    Current: serde::de::Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        let human_readable = deserializer.is_human_readable();
+        let content = content::Content::deserialize(deserializer)?;
+        let buffered = content::ContentRef::new(&content, human_readable);
+        if let Ok(old) = Old::deserialize(buffered) {
+            return Ok(Alt::Old(old));
+        }
+        if let Ok(current) = Current::deserialize(buffered) {
+            return Ok(Alt::Current(current));
+        }
+        Err(serde::de::Error::custom(
+            "data did not match any variant of untagged enum Alt",
+        ))
+    }
+}
+
+impl<Old, Current> Alt<Old, Current> {
+    pub fn from_old(value: Old) -> Self {
+        Alt::Old(value)
+    }
+
+    pub fn from_current(value: Current) -> Self {
+        Alt::Current(value)
+    }
+}
+
+/// `.into()` always builds the `Current` variant  
+/// There is no matching `From<Old>` since it would overlap with this impl when `Old` and
+/// `Current` are the same type, use [`Alt::from_old`] instead  
+impl<Old, Current> From<Current> for Alt<Old, Current> {
+    fn from(value: Current) -> Self {
+        Alt::Current(value)
+    }
+}
+
 impl<'de, Old, Current> serde::de::Deserialize<'de> for Compatible<Old, Current>
 where
     Current: CompatibleWith<Old>,
@@ -125,43 +740,286 @@ where
     }
 }
 
-#[test]
-pub fn test_simple() {
-    use serde::*;
-    #[derive(Serialize, Deserialize)]
-    pub struct Old {
-        pub a: i32,
-    }
-
-    #[derive(Serialize, Deserialize)]
-    pub struct New {
-        pub a: String,
-        pub b: i32,
-    }
+/// Serializes a borrowed `Compatible` in the current shape even while it holds the old variant,
+/// from [`Compatible::as_current`]  
+/// Old values are cloned and converted on every serialization  
+#[derive(Debug, Clone, Copy)]
+pub struct AsCurrent<'a, Old, Current>(&'a Compatible<Old, Current>);
 
-    impl From<Old> for New {
-        fn from(old: Old) -> Self {
-            New {
-                a: old.a.to_string(),
-                b: 0,
-            }
+impl<Old, Current> serde::ser::Serialize for AsCurrent<'_, Old, Current>
+where
+    Old: Clone,
+    Current: Serialize + CompatibleWith<Old>,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        match (self.0).0 {
+            Alt::Old(ref old) => upgrade::<_, Current>(old.clone()).serialize(serializer),
+            Alt::Current(ref current) => current.serialize(serializer),
         }
     }
+}
 
-    let old = Old { a: 1 };
-    let old_serialized = serde_json::to_string(&old).unwrap();
-    let migrated: Compatible<Old, New> = serde_json::from_str(&old_serialized).unwrap();
-    let migrated = migrated.into_current();
+/// Serializes `value` in the current shape, for `#[serde(serialize_with = "serialize_current")]`
+/// or for calling from a hand written `Serialize` impl  
+pub fn serialize_current<S, Old, Current>(
+    value: &Compatible<Old, Current>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::ser::Serializer,
+    Old: Clone,
+    Current: Serialize + CompatibleWith<Old>,
+{
+    value.as_current().serialize(serializer)
+}
 
-    assert_eq!(migrated.a, "1");
-    assert_eq!(migrated.b, 0);
+/// Serializes a sequence with every element in the current shape, for
+/// `#[serde(serialize_with = "serialize_seq_as_current")]` on `Vec` and slice fields  
+pub fn serialize_seq_as_current<S, Old, Current>(
+    values: &[Compatible<Old, Current>],
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::ser::Serializer,
+    Old: Clone,
+    Current: Serialize + CompatibleWith<Old>,
+{
+    serializer.collect_seq(values.iter().map(Compatible::as_current))
 }
 
-#[test]
-pub fn test_complex() {
-    use serde::*;
-    #[derive(Serialize, Deserialize)]
-    pub struct Dir {
+/// Compares against the raw old value, only equal while the old variant is still held  
+impl<Old: PartialEq, Current> PartialEq<Old> for Compatible<Old, Current> {
+    fn eq(&self, other: &Old) -> bool {
+        match self.0 {
+            Alt::Old(ref old) => old == other,
+            Alt::Current(_) => false,
+        }
+    }
+}
+
+impl<Old, T: ?Sized> Compatible<Old, std::sync::Arc<T>> {
+    /// Whether both sides already hold the current variant and share the same allocation with
+    /// `Arc::ptr_eq`  
+    /// Only the raw variant is checked, nothing is converted and an old value never matches,
+    /// normalize with [`Compatible::make_current`] first when old values should take part  
+    pub fn is_same_current(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Alt::Current(current), Alt::Current(other)) => std::sync::Arc::ptr_eq(current, other),
+            _ => false,
+        }
+    }
+}
+
+/// The shape a `Compatible` holds, or the shape it should be written as  
+#[derive(PartialEq, PartialOrd, Ord, Eq, Debug, Hash, Clone, Copy)]
+pub enum Version {
+    Old,
+    Current,
+}
+
+impl<Old, Current> Compatible<Old, Current> {
+    pub fn from_old(value: Old) -> Self {
+        Compatible(Alt::Old(value))
+    }
+
+    pub fn from_current(value: Current) -> Self {
+        Compatible(Alt::Current(value))
+    }
+
+    /// `Ok` with the current value, or `Err` with the old value if it hasn't been converted  
+    pub fn into_result(self) -> Result<Current, Old> {
+        match self.0 {
+            Alt::Old(old) => Err(old),
+            Alt::Current(current) => Ok(current),
+        }
+    }
+
+    /// The old value if it hasn't been converted yet, a current value is never downgraded  
+    /// The `Deserialize` impl converts eagerly, so this only sees old values built with
+    /// [`Compatible::from_old`] or [`Compatible::deserialize_version`]  
+    pub fn into_old(self) -> Option<Old> {
+        self.into_result().err()
+    }
+
+    /// Same as [`Compatible::into_result`] but the old value is wrapped in a [`ConversionError`]
+    /// so it can be propagated with `?`  
+    pub fn ok_or_old_err(self) -> Result<Current, ConversionError<Old>> {
+        self.into_result().map_err(ConversionError::new)
+    }
+
+    /// Converts with the fallible `TryCompatibleWith` impl  
+    pub fn try_into_current(self) -> Result<Current, Current::Error>
+    where
+        Current: TryCompatibleWith<Old>,
+    {
+        match self.0 {
+            Alt::Old(old) => {
+                counters::record();
+                Current::try_from_old(old)
+            }
+            Alt::Current(current) => Ok(current),
+        }
+    }
+
+    /// Converts with the fallible `TryCompatibleWith` impl and panics with `msg` and the
+    /// conversion error if it fails  
+    /// Already current values and infallible conversions never panic  
+    pub fn expect_current(self, msg: &str) -> Current
+    where
+        Current: TryCompatibleWith<Old>,
+        Current::Error: core::fmt::Debug,
+    {
+        match self.try_into_current() {
+            Ok(current) => current,
+            Err(err) => panic!("{msg}: {err:?}"),
+        }
+    }
+
+    /// Serialize in the current shape no matter which variant is held, so a dataset with a mix
+    /// of normalized and not yet normalized values is written out uniformly  
+    pub fn as_current(&self) -> AsCurrent<'_, Old, Current> {
+        AsCurrent(self)
+    }
+
+    /// Splits the wrapper into the held version and the raw variant, for formats that store
+    /// the tag and the payload separately  
+    pub fn into_parts(self) -> (Version, Alt<Old, Current>) {
+        (self.version(), self.0)
+    }
+
+    /// Borrow whichever variant is held without converting  
+    pub fn either_ref(&self) -> Alt<&Old, &Current> {
+        match self.0 {
+            Alt::Old(ref old) => Alt::Old(old),
+            Alt::Current(ref current) => Alt::Current(current),
+        }
+    }
+
+    /// Mutably borrow whichever variant is held without converting  
+    pub fn as_either_mut(&mut self) -> Alt<&mut Old, &mut Current> {
+        match self.0 {
+            Alt::Old(ref mut old) => Alt::Old(old),
+            Alt::Current(ref mut current) => Alt::Current(current),
+        }
+    }
+
+    /// Map the old value with `fo` or the current value with `fc`, keeping the held variant  
+    pub fn map_both<FO, FC, NO, NC>(self, fo: FO, fc: FC) -> Compatible<NO, NC>
+    where
+        FO: FnOnce(Old) -> NO,
+        FC: FnOnce(Current) -> NC,
+    {
+        match self.0 {
+            Alt::Old(old) => Compatible(Alt::Old(fo(old))),
+            Alt::Current(current) => Compatible(Alt::Current(fc(current))),
+        }
+    }
+
+    /// Whether the raw variant is current, nothing is converted  
+    pub fn is_current(&self) -> bool {
+        matches!(self.0, Alt::Current(_))
+    }
+
+    /// Whether the raw variant is still old, nothing is converted  
+    pub fn is_old(&self) -> bool {
+        matches!(self.0, Alt::Old(_))
+    }
+
+    /// The shape of the value currently held  
+    pub fn version(&self) -> Version {
+        match self.0 {
+            Alt::Old(_) => Version::Old,
+            Alt::Current(_) => Version::Current,
+        }
+    }
+
+    /// Whether the value is `Current::default()` once converted, an old value is cloned to be
+    /// converted  
+    /// Usable as `#[serde(skip_serializing_if = "Compatible::is_default")]`, which also covers
+    /// empty collections  
+    pub fn is_default(&self) -> bool
+    where
+        Old: Clone,
+        Current: CompatibleWith<Old> + Default + PartialEq,
+    {
+        match self.0 {
+            Alt::Old(ref old) => upgrade::<_, Current>(old.clone()) == Current::default(),
+            Alt::Current(ref current) => *current == Current::default(),
+        }
+    }
+
+    /// Downgrade to the old variant so the value serializes in the old shape again  
+    pub fn swap_to_old(self) -> Self
+    where
+        Old: From<Current>,
+    {
+        match self.0 {
+            Alt::Current(current) => Compatible(Alt::Old(Old::from(current))),
+            Alt::Old(_) => self,
+        }
+    }
+
+    /// Serialize as the requested `version` regardless of which variant is held  
+    /// Writing the old shape from a current value goes through the `Old: From<Current>` downgrade  
+    pub fn serialize_version<S>(&self, version: Version, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+        Old: Serialize + Clone + From<Current>,
+        Current: Serialize + Clone + CompatibleWith<Old>,
+    {
+        match (version, &self.0) {
+            (Version::Old, Alt::Old(old)) => old.serialize(serializer),
+            (Version::Old, Alt::Current(current)) => {
+                Old::from(current.clone()).serialize(serializer)
+            }
+            (Version::Current, Alt::Old(old)) => {
+                upgrade::<_, Current>(old.clone()).serialize(serializer)
+            }
+            (Version::Current, Alt::Current(current)) => current.serialize(serializer),
+        }
+    }
+}
+
+#[test]
+pub fn test_simple() {
+    use serde::*;
+    #[derive(Serialize, Deserialize)]
+    pub struct Old {
+        pub a: i32,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct New {
+        pub a: String,
+        pub b: i32,
+    }
+
+    impl From<Old> for New {
+        fn from(old: Old) -> Self {
+            New {
+                a: old.a.to_string(),
+                b: 0,
+            }
+        }
+    }
+
+    let old = Old { a: 1 };
+    let old_serialized = serde_json::to_string(&old).unwrap();
+    let migrated: Compatible<Old, New> = serde_json::from_str(&old_serialized).unwrap();
+    let migrated = migrated.into_current();
+
+    assert_eq!(migrated.a, "1");
+    assert_eq!(migrated.b, 0);
+}
+
+#[test]
+pub fn test_complex() {
+    use serde::*;
+    #[derive(Serialize, Deserialize)]
+    pub struct Dir {
         id: i64,
         name: String,
         path: String,
@@ -274,3 +1132,1700 @@ pub fn test_with() {
 
     assert_eq!(migrated.a, MyType("1".into()));
 }
+
+#[test]
+pub fn test_transparent() {
+    use serde::*;
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    pub struct Same {
+        pub a: i32,
+        pub b: String,
+    }
+
+    let value = Same {
+        a: 1,
+        b: "b".into(),
+    };
+    let serialized = serde_json::to_string(&value).unwrap();
+    let transparent: Transparent<Same> = serde_json::from_str(&serialized).unwrap();
+
+    assert_eq!(serde_json::to_string(&transparent).unwrap(), serialized);
+    assert_eq!(transparent.into_inner(), value);
+
+    #[derive(Deserialize)]
+    pub struct Holder {
+        #[serde(deserialize_with = "Transparent::<Same>::deserialize_transparent")]
+        pub same: Same,
+    }
+
+    let holder: Holder = serde_json::from_str(&format!(r#"{{"same":{serialized}}}"#)).unwrap();
+    assert_eq!(holder.same, value);
+    let old = Transparent::from_old(holder.same);
+    assert!(old.is_old());
+    assert_eq!(old.into_inner(), value);
+}
+
+#[test]
+pub fn test_serialize_version() {
+    use serde::*;
+    #[derive(Clone, Serialize, Deserialize)]
+    pub struct Old {
+        pub a: i32,
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    pub struct New {
+        pub a: String,
+    }
+
+    impl From<Old> for New {
+        fn from(old: Old) -> Self {
+            New {
+                a: old.a.to_string(),
+            }
+        }
+    }
+
+    impl From<New> for Old {
+        fn from(new: New) -> Self {
+            Old {
+                a: new.a.parse().unwrap_or_default(),
+            }
+        }
+    }
+
+    let value: Compatible<Old, New> = serde_json::from_str(r#"{"a":"1"}"#).unwrap();
+    assert_eq!(value.version(), Version::Current);
+
+    let write = |version| {
+        let mut buf = Vec::new();
+        value
+            .serialize_version(version, &mut serde_json::Serializer::new(&mut buf))
+            .unwrap();
+        String::from_utf8(buf).unwrap()
+    };
+
+    assert_eq!(write(Version::Old), r#"{"a":1}"#);
+    assert_eq!(write(Version::Current), r#"{"a":"1"}"#);
+}
+
+#[test]
+pub fn test_nested_untagged() {
+    use serde::*;
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    pub struct Old {
+        pub a: i32,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    pub struct New {
+        pub a: String,
+        pub b: i32,
+    }
+
+    impl From<Old> for New {
+        fn from(old: Old) -> Self {
+            New {
+                a: old.a.to_string(),
+                b: 0,
+            }
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    pub enum Outer {
+        Numbers(Vec<i32>),
+        Compatible(Compatible<Old, New>),
+    }
+
+    let values: Vec<Outer> = serde_json::from_str(r#"[{"a":1},{"a":"2","b":3},[4,5]]"#).unwrap();
+    let mut values = values.into_iter();
+
+    let Some(Outer::Compatible(old)) = values.next() else {
+        panic!("expected the old shape to match the compatible variant");
+    };
+    assert_eq!(
+        old.into_current(),
+        New {
+            a: "1".into(),
+            b: 0
+        }
+    );
+
+    let Some(Outer::Compatible(current)) = values.next() else {
+        panic!("expected the current shape to match the compatible variant");
+    };
+    assert_eq!(
+        current.into_current(),
+        New {
+            a: "2".into(),
+            b: 3
+        }
+    );
+
+    let Some(Outer::Numbers(numbers)) = values.next() else {
+        panic!("expected the sequence to match the numbers variant");
+    };
+    assert_eq!(numbers, vec![4, 5]);
+}
+
+#[test]
+pub fn test_ok_or_old_err() {
+    use std::error::Error;
+
+    #[derive(Debug, PartialEq)]
+    pub struct Old(i32);
+
+    #[derive(Debug, PartialEq)]
+    pub struct New(String);
+
+    fn current(value: Compatible<Old, New>) -> Result<New, Box<dyn Error>> {
+        Ok(value.ok_or_old_err()?)
+    }
+
+    assert_eq!(
+        current(Compatible::from_current(New("1".into()))).unwrap(),
+        New("1".into())
+    );
+
+    let err = current(Compatible::from_old(Old(1))).unwrap_err();
+    let err = err.downcast::<ConversionError<Old>>().unwrap();
+    assert_eq!(err.into_old(), Old(1));
+}
+
+#[test]
+pub fn test_with_fn() {
+    use serde::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    pub struct MyType(String);
+
+    fn from_number(value: i32) -> MyType {
+        MyType(format!("#{value}"))
+    }
+
+    compatible_with_fn!(deserialize_my_type, i32, MyType, from_number);
+
+    #[derive(Deserialize)]
+    pub struct New {
+        #[serde(deserialize_with = "deserialize_my_type")]
+        pub a: MyType,
+    }
+
+    let migrated: New = serde_json::from_str(r#"{"a":1}"#).unwrap();
+    assert_eq!(migrated.a, MyType("#1".into()));
+
+    let current: New = serde_json::from_str(r#"{"a":"2"}"#).unwrap();
+    assert_eq!(current.a, MyType("2".into()));
+}
+
+#[test]
+pub fn test_default() {
+    use serde::*;
+
+    #[derive(Default)]
+    pub struct OldLimits {
+        pub max: u8,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    pub struct Limits {
+        pub max: u32,
+        pub min: u32,
+    }
+
+    impl From<OldLimits> for Limits {
+        fn from(old: OldLimits) -> Self {
+            Limits {
+                max: u32::from(old.max) + 10,
+                min: 1,
+            }
+        }
+    }
+
+    compatible_default!(default_limits, OldLimits, Limits);
+
+    #[derive(Deserialize)]
+    pub struct Config {
+        #[serde(default = "default_limits")]
+        pub limits: Limits,
+    }
+
+    let config: Config = serde_json::from_str("{}").unwrap();
+    assert_eq!(config.limits, Limits { max: 10, min: 1 });
+
+    let config: Config = serde_json::from_str(r#"{"limits":{"max":5,"min":2}}"#).unwrap();
+    assert_eq!(config.limits, Limits { max: 5, min: 2 });
+}
+
+#[test]
+pub fn test_alt_from() {
+    #[derive(Debug, PartialEq)]
+    pub struct Old(i32);
+
+    #[derive(Debug, PartialEq)]
+    pub struct New(String);
+
+    let current: Alt<Old, New> = New("1".into()).into();
+    assert_eq!(current, Alt::Current(New("1".into())));
+    assert_eq!(Alt::<Old, New>::from_old(Old(1)), Alt::Old(Old(1)));
+    assert_eq!(
+        Alt::<Old, New>::from_current(New("1".into())),
+        Alt::Current(New("1".into()))
+    );
+
+    let same: Alt<i32, i32> = 1.into();
+    assert_eq!(same, Alt::Current(1));
+}
+
+#[test]
+pub fn test_key_order() {
+    use serde::*;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Old {
+        pub name: String,
+        pub id: i64,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct New {
+        pub id: i64,
+        pub name: String,
+        pub labels: BTreeMap<String, String>,
+    }
+
+    impl From<Old> for New {
+        fn from(old: Old) -> Self {
+            let labels = [("z", "last"), ("a", "first")]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            New {
+                id: old.id,
+                name: old.name,
+                labels,
+            }
+        }
+    }
+
+    let migrated: Compatible<Old, New> = serde_json::from_str(r#"{"name":"a","id":1}"#).unwrap();
+    assert_eq!(
+        serde_json::to_string(&migrated).unwrap(),
+        r#"{"id":1,"name":"a","labels":{"a":"first","z":"last"}}"#
+    );
+}
+
+#[test]
+pub fn test_deserialize_version() {
+    use serde::*;
+
+    /// Old data only stored the numeric id of what is now `Ref::Named`
+    #[derive(Debug, Deserialize, PartialEq)]
+    pub struct OldRef(i32);
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(untagged)]
+    pub enum Ref {
+        Index(i32),
+        Named(String),
+    }
+
+    impl From<OldRef> for Ref {
+        fn from(old: OldRef) -> Self {
+            Ref::Named(format!("id-{}", old.0))
+        }
+    }
+
+    // Without a hint the old shape wins and current data is misread
+    let guessed: Compatible<OldRef, Ref> = serde_json::from_str("5").unwrap();
+    assert_eq!(guessed.into_current(), Ref::Named("id-5".into()));
+
+    let mut de = serde_json::Deserializer::from_str("5");
+    let current =
+        Compatible::<OldRef, Ref>::deserialize_version(Version::Current, &mut de).unwrap();
+    assert_eq!(current.into_current(), Ref::Index(5));
+
+    let mut de = serde_json::Deserializer::from_str("5");
+    let old = Compatible::<OldRef, Ref>::deserialize_version(Version::Old, &mut de).unwrap();
+    assert_eq!(old.into_current(), Ref::Named("id-5".into()));
+}
+
+#[test]
+pub fn test_swap_to_old() {
+    use serde::*;
+    #[derive(Serialize, Deserialize)]
+    pub struct Old {
+        pub a: i32,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct New {
+        pub a: String,
+        pub b: i32,
+    }
+
+    impl From<Old> for New {
+        fn from(old: Old) -> Self {
+            New {
+                a: old.a.to_string(),
+                b: 0,
+            }
+        }
+    }
+
+    impl From<New> for Old {
+        fn from(new: New) -> Self {
+            Old {
+                a: new.a.parse().unwrap_or_default(),
+            }
+        }
+    }
+
+    let migrated: Compatible<Old, New> = serde_json::from_str(r#"{"a":1}"#).unwrap();
+    let mut current = migrated.into_current();
+    current.a = "2".into();
+
+    let legacy = Compatible::<Old, New>::from_current(current).swap_to_old();
+    assert_eq!(legacy.version(), Version::Old);
+    assert_eq!(serde_json::to_string(&legacy).unwrap(), r#"{"a":2}"#);
+}
+
+#[test]
+pub fn test_skip_serializing_if_default() {
+    use serde::*;
+    #[derive(Clone, Serialize, Deserialize)]
+    pub struct OldTags(String);
+
+    impl From<OldTags> for Vec<String> {
+        fn from(old: OldTags) -> Self {
+            old.0
+                .split(',')
+                .filter(|tag| !tag.is_empty())
+                .map(String::from)
+                .collect()
+        }
+    }
+
+    #[derive(Serialize)]
+    pub struct Item {
+        pub id: i32,
+        #[serde(skip_serializing_if = "Compatible::is_default")]
+        pub tags: Compatible<OldTags, Vec<String>>,
+    }
+
+    let empty = Item {
+        id: 1,
+        tags: Compatible::from_old(OldTags("".into())),
+    };
+    assert_eq!(serde_json::to_string(&empty).unwrap(), r#"{"id":1}"#);
+
+    let tagged = Item {
+        id: 2,
+        tags: serde_json::from_str(r#""a,b""#).unwrap(),
+    };
+    assert_eq!(
+        serde_json::to_string(&tagged).unwrap(),
+        r#"{"id":2,"tags":["a","b"]}"#
+    );
+}
+
+#[test]
+pub fn test_assume_current() {
+    use serde::*;
+    #[derive(Debug, Deserialize, PartialEq)]
+    pub struct MyType(String);
+
+    impl From<i32> for MyType {
+        fn from(value: i32) -> Self {
+            MyType(value.to_string())
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct New {
+        #[serde(deserialize_with = "Compatible::<i32, MyType>::deserialize_assume_current")]
+        pub a: MyType,
+    }
+
+    let current: New = serde_json::from_str(r#"{"a":"1"}"#).unwrap();
+    assert_eq!(current.a, MyType("1".into()));
+    assert!(serde_json::from_str::<New>(r#"{"a":1}"#).is_err());
+}
+
+#[test]
+pub fn test_either_ref() {
+    let describe = |value: &Compatible<i32, String>| match value.either_ref() {
+        Alt::Old(old) => format!("old {old}"),
+        Alt::Current(current) => format!("current {current}"),
+    };
+
+    assert_eq!(describe(&Compatible::from_old(1)), "old 1");
+    assert_eq!(describe(&Compatible::from_current("a".into())), "current a");
+}
+
+#[test]
+pub fn test_cloned_current() {
+    pub struct Holder {
+        pub value: Compatible<i32, i64>,
+    }
+
+    impl Holder {
+        pub fn doubled(&self) -> i64 {
+            self.value.cloned_current() * 2
+        }
+    }
+
+    let old = Holder {
+        value: Compatible::from_old(2),
+    };
+    assert_eq!(old.doubled(), 4);
+    assert_eq!(old.value.version(), Version::Old);
+
+    let current = Holder {
+        value: Compatible::from_current(3),
+    };
+    assert_eq!(current.doubled(), 6);
+}
+
+#[test]
+pub fn test_normalize() {
+    pub struct Settings {
+        pub retries: Compatible<u8, u32>,
+    }
+
+    fn upgrade(settings: &mut Settings) {
+        settings.retries.normalize();
+    }
+
+    let mut settings = Settings {
+        retries: Compatible::from_old(3),
+    };
+    upgrade(&mut settings);
+    assert_eq!(settings.retries.version(), Version::Current);
+    assert_eq!(settings.retries.into_current(), 3);
+}
+
+#[test]
+pub fn test_eq_old() {
+    #[derive(Debug, PartialEq)]
+    pub struct Old(i32);
+
+    #[derive(Debug, PartialEq)]
+    pub struct New(i64);
+
+    impl From<Old> for New {
+        fn from(old: Old) -> Self {
+            New(old.0.into())
+        }
+    }
+
+    let value: Compatible<Old, New> = Compatible::from_old(Old(1));
+    assert!(value == Old(1));
+    assert!(value != Old(2));
+
+    let value = value.make_current();
+    assert!(value != Old(1));
+}
+
+#[test]
+pub fn test_flatten_extra() {
+    use serde::*;
+    use serde_json::Value;
+    use std::collections::HashMap;
+
+    /// Old records kept ad-hoc keys next to the known ones
+    #[derive(Deserialize)]
+    pub struct Old {
+        pub name: String,
+        #[serde(flatten)]
+        pub rest: HashMap<String, Value>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    pub struct New {
+        pub name: String,
+        pub extra: HashMap<String, Value>,
+    }
+
+    impl From<Old> for New {
+        fn from(old: Old) -> Self {
+            New {
+                name: old.name,
+                extra: old.rest,
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct Document {
+        #[serde(deserialize_with = "Compatible::<Old, New>::deserialize_current_first")]
+        pub item: New,
+    }
+
+    let expected = New {
+        name: "a".into(),
+        extra: HashMap::from([
+            ("color".to_string(), Value::from("red")),
+            ("size".to_string(), Value::from(3)),
+        ]),
+    };
+
+    let old: Document =
+        serde_json::from_str(r#"{"item":{"name":"a","color":"red","size":3}}"#).unwrap();
+    assert_eq!(old.item, expected);
+
+    let current: Document =
+        serde_json::from_str(r#"{"item":{"name":"a","extra":{"color":"red","size":3}}}"#).unwrap();
+    assert_eq!(current.item, expected);
+}
+
+#[test]
+pub fn test_internally_tagged_old() {
+    use serde::*;
+    #[derive(Deserialize)]
+    #[serde(tag = "type")]
+    pub enum OldShape {
+        Circle { radius: f64 },
+        Square { side: f64 },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    pub struct Shape {
+        pub kind: String,
+        pub size: f64,
+    }
+
+    impl From<OldShape> for Shape {
+        fn from(old: OldShape) -> Self {
+            match old {
+                OldShape::Circle { radius } => Shape {
+                    kind: "circle".into(),
+                    size: radius,
+                },
+                OldShape::Square { side } => Shape {
+                    kind: "square".into(),
+                    size: side,
+                },
+            }
+        }
+    }
+
+    let shapes: Vec<Compatible<OldShape, Shape>> = serde_json::from_str(
+        r#"[{"type":"Circle","radius":1.5},{"type":"Square","side":2.0},{"kind":"hexagon","size":3.0}]"#,
+    )
+    .unwrap();
+    let shapes = shapes
+        .into_iter()
+        .map(Compatible::into_current)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        shapes,
+        vec![
+            Shape {
+                kind: "circle".into(),
+                size: 1.5
+            },
+            Shape {
+                kind: "square".into(),
+                size: 2.0
+            },
+            Shape {
+                kind: "hexagon".into(),
+                size: 3.0
+            },
+        ]
+    );
+}
+
+#[test]
+pub fn test_compatible_any() {
+    use serde::*;
+    #[derive(Debug, Deserialize, PartialEq)]
+    pub struct Port(u16);
+
+    #[derive(Deserialize)]
+    pub struct OldPort {
+        pub port: u16,
+    }
+
+    impl From<u16> for Port {
+        fn from(old: u16) -> Self {
+            Port(old)
+        }
+    }
+
+    impl From<String> for Port {
+        fn from(old: String) -> Self {
+            Port(old.parse().unwrap_or(80))
+        }
+    }
+
+    impl From<OldPort> for Port {
+        fn from(old: OldPort) -> Self {
+            Port(old.port)
+        }
+    }
+
+    compatible_any! {
+        pub type AnyPort = Port; u16, String, OldPort
+    }
+
+    let ports: Vec<AnyPort> = serde_json::from_str(r#"[8080, "8081", {"port": 8082}]"#).unwrap();
+    let ports = ports
+        .into_iter()
+        .map(Compatible::into_current)
+        .collect::<Vec<_>>();
+    assert_eq!(ports, vec![Port(8080), Port(8081), Port(8082)]);
+}
+
+#[test]
+pub fn test_expect_current() {
+    #[derive(Debug, PartialEq)]
+    pub struct Port(u16);
+
+    impl TryFrom<String> for Port {
+        type Error = core::num::ParseIntError;
+        fn try_from(old: String) -> Result<Self, Self::Error> {
+            old.parse().map(Port)
+        }
+    }
+
+    let old: Compatible<String, Port> = Compatible::from_old("8080".into());
+    assert_eq!(old.expect_current("valid port"), Port(8080));
+
+    let current: Compatible<String, Port> = Compatible::from_current(Port(1));
+    assert_eq!(current.expect_current("valid port"), Port(1));
+
+    let infallible: Compatible<u8, u32> = Compatible::from_old(1);
+    assert_eq!(infallible.expect_current("never fails"), 1);
+}
+
+#[test]
+#[should_panic(expected = "valid port: ParseIntError")]
+pub fn test_expect_current_panics() {
+    #[derive(Debug, PartialEq)]
+    pub struct Port(u16);
+
+    impl TryFrom<String> for Port {
+        type Error = core::num::ParseIntError;
+        fn try_from(old: String) -> Result<Self, Self::Error> {
+            old.parse().map(Port)
+        }
+    }
+
+    let old: Compatible<String, Port> = Compatible::from_old("http".into());
+    old.expect_current("valid port");
+}
+
+#[test]
+pub fn test_flatten_map_of_compatible() {
+    use serde::*;
+    use std::collections::HashMap;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    pub struct OldLimit {
+        pub max: u32,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    pub struct Limit {
+        pub min: u32,
+        pub max: u32,
+    }
+
+    impl From<OldLimit> for Limit {
+        fn from(old: OldLimit) -> Self {
+            Limit {
+                min: 0,
+                max: old.max,
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct Limits {
+        pub version: u32,
+        #[serde(flatten)]
+        pub limits: HashMap<String, Compatible<OldLimit, Limit>>,
+    }
+
+    let limits: Limits =
+        serde_json::from_str(r#"{"version":2,"cpu":{"max":4},"memory":{"min":1,"max":8}}"#)
+            .unwrap();
+    assert_eq!(limits.version, 2);
+    let mut limits = limits
+        .limits
+        .into_iter()
+        .map(|(k, v)| (k, v.into_current()))
+        .collect::<Vec<_>>();
+    limits.sort_by(|a, b| a.0.cmp(&b.0));
+
+    assert_eq!(
+        limits,
+        vec![
+            ("cpu".to_string(), Limit { min: 0, max: 4 }),
+            ("memory".to_string(), Limit { min: 1, max: 8 }),
+        ]
+    );
+}
+
+#[test]
+pub fn test_borrowed() {
+    use serde::*;
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Old<'a> {
+        #[serde(borrow)]
+        pub name: &'a str,
+    }
+
+    #[derive(Deserialize)]
+    pub struct New<'a> {
+        #[serde(borrow)]
+        pub name: &'a str,
+        pub id: u32,
+    }
+
+    impl<'a> From<Old<'a>> for New<'a> {
+        fn from(old: Old<'a>) -> Self {
+            New {
+                name: old.name,
+                id: 0,
+            }
+        }
+    }
+
+    let borrows_input = |input: &str, name: &str| {
+        let range = input.as_bytes().as_ptr_range();
+        range.contains(&name.as_ptr())
+    };
+
+    let input = r#"{"name":"old"}"#;
+    let old: Compatible<Old, New> = serde_json::from_str(input).unwrap();
+    let old = old.into_current();
+    assert_eq!(old.name, "old");
+    assert!(borrows_input(input, old.name));
+
+    let input = r#"{"name":"current","id":1}"#;
+    let current: Compatible<Old, New> = serde_json::from_str(input).unwrap();
+    let current = current.into_current();
+    assert_eq!((current.name, current.id), ("current", 1));
+    assert!(borrows_input(input, current.name));
+}
+
+#[test]
+pub fn test_into_parts() {
+    let (version, payload) = Compatible::<u8, String>::from_old(1).into_parts();
+    assert_eq!(version, Version::Old);
+    assert_eq!(payload, Alt::Old(1));
+
+    let (version, payload) = Compatible::<u8, String>::from_current("a".into()).into_parts();
+    assert_eq!(version, Version::Current);
+    let Alt::Current(current) = payload else {
+        panic!("expected the current payload");
+    };
+    assert_eq!(current, "a");
+}
+
+#[test]
+pub fn test_nullable_old() {
+    use serde::*;
+    #[derive(Deserialize)]
+    pub struct OldTimeout(u32);
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    pub struct Timeout {
+        pub secs: u32,
+    }
+
+    impl From<Option<OldTimeout>> for Timeout {
+        fn from(old: Option<OldTimeout>) -> Self {
+            Timeout {
+                secs: old.map_or(30, |old| old.0),
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct Config {
+        pub timeout: Compatible<Option<OldTimeout>, Timeout>,
+    }
+
+    let parse = |input| {
+        serde_json::from_str::<Config>(input)
+            .unwrap()
+            .timeout
+            .into_current()
+    };
+    assert_eq!(parse(r#"{"timeout":null}"#), Timeout { secs: 30 });
+    assert_eq!(parse(r#"{"timeout":5}"#), Timeout { secs: 5 });
+    assert_eq!(parse(r#"{"timeout":{"secs":10}}"#), Timeout { secs: 10 });
+}
+
+#[test]
+pub fn test_as_current() {
+    use serde::*;
+    #[derive(Clone, Serialize)]
+    pub struct Old {
+        pub a: i32,
+    }
+
+    #[derive(Serialize)]
+    pub struct New {
+        pub a: String,
+    }
+
+    impl From<Old> for New {
+        fn from(old: Old) -> Self {
+            New {
+                a: old.a.to_string(),
+            }
+        }
+    }
+
+    let values = [
+        Compatible::<Old, New>::from_old(Old { a: 1 }),
+        Compatible::from_current(New { a: "2".into() }),
+    ];
+    assert_eq!(
+        serde_json::to_string(&values).unwrap(),
+        r#"[{"a":1},{"a":"2"}]"#
+    );
+
+    let current = values
+        .iter()
+        .map(Compatible::as_current)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        serde_json::to_string(&current).unwrap(),
+        r#"[{"a":"1"},{"a":"2"}]"#
+    );
+}
+
+#[test]
+pub fn test_map_both() {
+    let old =
+        Compatible::<i32, String>::from_old(2).map_both(|old| old * 2, |current| current.len());
+    assert_eq!(old.version(), Version::Old);
+    assert_eq!(old.into_result(), Err(4));
+
+    let current = Compatible::<i32, String>::from_current("abc".into())
+        .map_both(|old| old.to_string(), |current| current.len() as u64);
+    assert_eq!(current.version(), Version::Current);
+    assert_eq!(current.into_result(), Ok(3));
+}
+
+#[test]
+pub fn test_into_old() {
+    let parse = |version, input| {
+        Compatible::<i32, String>::deserialize_version(
+            version,
+            &mut serde_json::Deserializer::from_str(input),
+        )
+        .unwrap()
+    };
+
+    assert_eq!(parse(Version::Old, "1").into_old(), Some(1));
+    assert_eq!(parse(Version::Current, r#""1""#).into_old(), None);
+    assert_eq!(
+        serde_json::from_str::<Compatible<u8, u32>>("1")
+            .unwrap()
+            .into_old(),
+        None
+    );
+}
+
+#[test]
+pub fn test_from_str_csv_column() {
+    use core::str::FromStr;
+
+    #[derive(Debug)]
+    pub struct OldFlag(bool);
+
+    impl FromStr for OldFlag {
+        type Err = ();
+        fn from_str(value: &str) -> Result<Self, ()> {
+            match value {
+                "yes" => Ok(OldFlag(true)),
+                "no" => Ok(OldFlag(false)),
+                _ => Err(()),
+            }
+        }
+    }
+
+    impl From<OldFlag> for bool {
+        fn from(old: OldFlag) -> Self {
+            old.0
+        }
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Row {
+        pub name: String,
+        #[serde(deserialize_with = "Compatible::<OldFlag, bool>::deserialize_from_str")]
+        pub enabled: bool,
+    }
+
+    // csv hands every column to serde as a string, `yes` only becomes a flag through `FromStr`
+    let data = "name,enabled\na,yes\nb,false\nc,maybe\n";
+    let rows = csv::Reader::from_reader(data.as_bytes())
+        .deserialize::<Row>()
+        .collect::<Vec<_>>();
+
+    assert_eq!(rows.len(), 3);
+    assert_eq!(
+        rows[0].as_ref().unwrap(),
+        &Row {
+            name: "a".into(),
+            enabled: true
+        }
+    );
+    assert_eq!(
+        rows[1].as_ref().unwrap(),
+        &Row {
+            name: "b".into(),
+            enabled: false
+        }
+    );
+    assert!(rows[2].is_err());
+}
+
+#[test]
+pub fn test_is_same_current() {
+    use std::sync::Arc;
+
+    /// No `Default` for the pointee, so the check can't lean on a placeholder value
+    #[derive(Debug)]
+    pub struct Handle(u32);
+
+    pub struct OldHandle(u32);
+
+    impl From<OldHandle> for Arc<Handle> {
+        fn from(old: OldHandle) -> Self {
+            Arc::new(Handle(old.0))
+        }
+    }
+
+    let shared = Arc::new(Handle(1));
+    let first = Compatible::<OldHandle, Arc<Handle>>::from_current(shared.clone());
+    let second = Compatible::from_current(shared);
+    let distinct = Compatible::from_current(Arc::new(Handle(1)));
+    let old = Compatible::from_old(OldHandle(1));
+
+    assert!(first.is_same_current(&second));
+    assert!(!first.is_same_current(&distinct));
+    assert!(!first.is_same_current(&old));
+    assert!(!old.is_same_current(&old));
+
+    let normalized = old.make_current();
+    assert!(normalized.is_same_current(&normalized));
+    assert_eq!(normalized.into_current().0, 1);
+}
+
+#[test]
+pub fn test_take() {
+    pub struct Holder {
+        pub values: Compatible<String, Vec<u8>>,
+    }
+
+    let mut holder = Holder {
+        values: Compatible::from_old("ab".into()),
+    };
+    assert_eq!(holder.values.take(), b"ab");
+    assert_eq!(holder.values.into_result(), Ok(Vec::new()));
+}
+
+#[test]
+pub fn test_expect_version() {
+    assert_eq!(
+        Compatible::<u8, u32>::from_old(1).expect_version(Version::Old),
+        1
+    );
+    assert_eq!(
+        Compatible::<u8, u32>::from_current(2).expect_version(Version::Current),
+        2
+    );
+}
+
+#[test]
+pub fn test_deserialize_expect_version() {
+    let parse = |input: &str, expected| {
+        Compatible::<String, Vec<u8>>::deserialize_expect_version(
+            &mut serde_json::Deserializer::from_str(input),
+            expected,
+        )
+    };
+
+    assert_eq!(parse(r#""ab""#, Version::Old).unwrap(), b"ab");
+    assert_eq!(parse("[1]", Version::Current).unwrap(), [1]);
+    let err = parse("[1]", Version::Old).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("expected a value in the Old shape but found Current"));
+    assert!(parse(r#""ab""#, Version::Current).is_err());
+}
+
+#[test]
+#[should_panic(expected = "expected a value in the Old shape but found Current")]
+pub fn test_expect_version_mismatch() {
+    Compatible::<u8, u32>::from_current(2).expect_version(Version::Old);
+}
+
+#[test]
+pub fn test_cow_with() {
+    use std::borrow::Cow;
+
+    #[derive(Debug, Deserialize, PartialEq, Clone)]
+    pub struct New {
+        pub a: String,
+    }
+
+    impl From<i32> for New {
+        fn from(old: i32) -> Self {
+            New { a: old.to_string() }
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct Holder {
+        #[serde(deserialize_with = "Compatible::<i32, New>::deserialize_cow_with")]
+        pub value: Cow<'static, New>,
+    }
+
+    let holder: Holder = serde_json::from_str(r#"{"value":1}"#).unwrap();
+    assert!(matches!(holder.value, Cow::Owned(_)));
+    assert_eq!(*holder.value, New { a: "1".into() });
+}
+
+#[test]
+pub fn test_with_current() {
+    let mut value = Compatible::<String, Vec<u8>>::from_old("abc".into());
+    assert_eq!(value.with_current(|bytes| bytes.len()), 3);
+    assert_eq!(value.version(), Version::Current);
+    assert_eq!(value.into_result(), Ok(b"abc".to_vec()));
+}
+
+#[test]
+pub fn test_is_current_is_old() {
+    let parse = |version, input| {
+        Compatible::<u8, String>::deserialize_version(
+            version,
+            &mut serde_json::Deserializer::from_str(input),
+        )
+        .unwrap()
+    };
+
+    let old = parse(Version::Old, "1");
+    assert!(old.is_old() && !old.is_current());
+    let current = parse(Version::Current, r#""1""#);
+    assert!(current.is_current() && !current.is_old());
+}
+
+#[test]
+pub fn test_version_out() {
+    compatible_version_out!(number_version, u8, u32);
+
+    #[derive(Deserialize)]
+    pub struct Counter {
+        #[serde(deserialize_with = "number_version::deserialize")]
+        pub count: u32,
+    }
+
+    assert_eq!(number_version::last_version(), None);
+    let old: Counter = serde_json::from_str(r#"{"count":1}"#).unwrap();
+    assert_eq!(old.count, 1);
+    assert_eq!(number_version::last_version(), Some(Version::Old));
+
+    let current: Counter = serde_json::from_str(r#"{"count":1000}"#).unwrap();
+    assert_eq!(current.count, 1000);
+    assert_eq!(number_version::last_version(), Some(Version::Current));
+}
+
+#[test]
+pub fn test_with_fn_ordered_map() {
+    #[derive(Deserialize)]
+    pub struct Entry {
+        pub key: String,
+        pub value: u32,
+    }
+
+    // Neither side is a local type, so no `From` impl can connect them
+    fn by_key(old: Vec<Entry>) -> Vec<(String, u32)> {
+        old.into_iter()
+            .map(|entry| (entry.key, entry.value))
+            .collect()
+    }
+    compatible_with_fn!(deserialize_ordered, Vec<Entry>, Vec<(String, u32)>, by_key);
+
+    #[derive(Deserialize)]
+    pub struct Config {
+        #[serde(deserialize_with = "deserialize_ordered")]
+        pub limits: Vec<(String, u32)>,
+    }
+
+    let old: Config =
+        serde_json::from_str(r#"{"limits":[{"key":"z","value":1},{"key":"a","value":2}]}"#)
+            .unwrap();
+    assert_eq!(old.limits, [("z".into(), 1), ("a".into(), 2)]);
+
+    let current: Config = serde_json::from_str(r#"{"limits":[["z",1],["a",2]]}"#).unwrap();
+    assert_eq!(current.limits, old.limits);
+}
+
+#[test]
+pub fn test_current_only() {
+    #[derive(Deserialize)]
+    pub struct Old {
+        pub a: i32,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    pub struct New {
+        pub a: String,
+    }
+
+    impl From<Old> for New {
+        fn from(old: Old) -> Self {
+            New {
+                a: old.a.to_string(),
+            }
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Request {
+        #[serde(deserialize_with = "Compatible::<Old, New>::deserialize_current_only")]
+        pub body: New,
+    }
+
+    let err = serde_json::from_str::<Request>(r#"{"body":{"a":1}}"#).unwrap_err();
+    assert!(err.to_string().contains("no longer accepted"));
+
+    let current: Request = serde_json::from_str(r#"{"body":{"a":"1"}}"#).unwrap();
+    assert_eq!(current.body, New { a: "1".into() });
+}
+
+#[test]
+pub fn test_transparent_current() {
+    #[derive(Serialize, Deserialize)]
+    pub struct OldContact {
+        pub email: String,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    #[serde(transparent)]
+    pub struct Email(String);
+
+    impl From<OldContact> for Email {
+        fn from(old: OldContact) -> Self {
+            Email(old.email)
+        }
+    }
+
+    let old: Compatible<OldContact, Email> =
+        serde_json::from_str(r#"{"email":"a@example.com"}"#).unwrap();
+    let serialized = serde_json::to_string(&old).unwrap();
+    assert_eq!(serialized, r#""a@example.com""#);
+
+    let current: Compatible<OldContact, Email> = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(old.into_current(), Email("a@example.com".into()));
+    assert_eq!(current.into_current(), Email("a@example.com".into()));
+}
+
+#[test]
+pub fn test_normalize_all() {
+    #[derive(Debug, PartialEq)]
+    pub struct New(String);
+
+    impl From<i32> for New {
+        fn from(old: i32) -> Self {
+            New(old.to_string())
+        }
+    }
+
+    let values: [Compatible<i32, New>; 3] = [
+        Compatible::from_old(1),
+        Compatible::from_current(New("two".into())),
+        Compatible::from_old(3),
+    ];
+    assert_eq!(
+        Compatible::normalize_all(values),
+        [New("1".into()), New("two".into()), New("3".into())]
+    );
+}
+
+#[test]
+pub fn test_externally_to_adjacently_tagged() {
+    #[derive(Debug, Serialize, Deserialize)]
+    pub enum OldShape {
+        Circle { radius: f64 },
+        Square(f64),
+        Empty,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    #[serde(tag = "kind", content = "data")]
+    pub enum Shape {
+        Circle { radius: f64 },
+        Square(f64),
+        Empty,
+    }
+
+    impl From<OldShape> for Shape {
+        fn from(old: OldShape) -> Self {
+            match old {
+                OldShape::Circle { radius } => Shape::Circle { radius },
+                OldShape::Square(side) => Shape::Square(side),
+                OldShape::Empty => Shape::Empty,
+            }
+        }
+    }
+
+    let old: Vec<Compatible<OldShape, Shape>> =
+        serde_json::from_str(r#"[{"Circle":{"radius":1.0}},{"Square":2.0},"Empty"]"#).unwrap();
+    let current = serde_json::to_string(&old).unwrap();
+    assert_eq!(
+        current,
+        r#"[{"kind":"Circle","data":{"radius":1.0}},{"kind":"Square","data":2.0},{"kind":"Empty"}]"#
+    );
+
+    let reread: Vec<Compatible<OldShape, Shape>> = serde_json::from_str(&current).unwrap();
+    assert_eq!(
+        reread
+            .into_iter()
+            .map(Compatible::into_current)
+            .collect::<Vec<_>>(),
+        [
+            Shape::Circle { radius: 1.0 },
+            Shape::Square(2.0),
+            Shape::Empty
+        ]
+    );
+}
+
+#[test]
+pub fn test_upgrade() {
+    let mut records = vec![
+        Compatible::<u8, u32>::from_old(1),
+        Compatible::from_current(2),
+    ];
+    let upgraded = |records: &mut Vec<Compatible<u8, u32>>| {
+        records
+            .iter_mut()
+            .map(Compatible::upgrade)
+            .filter(|&changed| changed)
+            .count()
+    };
+
+    assert_eq!(upgraded(&mut records), 1);
+    assert_eq!(upgraded(&mut records), 0);
+    assert!(!records[0].upgrade());
+    assert_eq!(records[0], Compatible::from_current(1));
+}
+
+#[test]
+pub fn test_serialize_current() {
+    use serde::ser::SerializeStruct;
+
+    pub struct Job {
+        pub name: String,
+        pub payload: Compatible<String, Vec<u8>>,
+    }
+
+    pub struct Payload<'a>(&'a Compatible<String, Vec<u8>>);
+
+    impl Serialize for Payload<'_> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serialize_current(self.0, serializer)
+        }
+    }
+
+    impl Serialize for Job {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut job = serializer.serialize_struct("Job", 2)?;
+            job.serialize_field("name", &self.name)?;
+            job.serialize_field("payload", &Payload(&self.payload))?;
+            job.end()
+        }
+    }
+
+    let job = Job {
+        name: "a".into(),
+        payload: Compatible::from_old("ab".into()),
+    };
+    assert_eq!(
+        serde_json::to_string(&job).unwrap(),
+        r#"{"name":"a","payload":[97,98]}"#
+    );
+}
+
+#[test]
+pub fn test_integer_discriminant() {
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Status {
+        Active,
+        Suspended,
+        Unknown,
+    }
+
+    impl From<i64> for Status {
+        fn from(old: i64) -> Self {
+            match old {
+                0 => Status::Active,
+                1 => Status::Suspended,
+                _ => Status::Unknown,
+            }
+        }
+    }
+
+    let statuses: Vec<Compatible<i64, Status>> =
+        serde_json::from_str(r#"[0,1,7,"suspended"]"#).unwrap();
+    assert_eq!(
+        serde_json::to_string(&statuses).unwrap(),
+        r#"["active","suspended","unknown","suspended"]"#
+    );
+}
+
+#[test]
+pub fn test_as_either_mut() {
+    #[derive(Debug, PartialEq)]
+    pub struct Timeout(Option<u32>);
+
+    impl From<i32> for Timeout {
+        fn from(old: i32) -> Self {
+            Timeout(u32::try_from(old).ok())
+        }
+    }
+
+    let mut timeout = Compatible::<i32, Timeout>::from_old(0);
+    // Old data used 0 as the "no timeout" sentinel
+    if let Alt::Old(old @ 0) = timeout.as_either_mut() {
+        *old = -1;
+    }
+    assert_eq!(timeout.version(), Version::Old);
+    assert_eq!(timeout.into_current(), Timeout(None));
+
+    let mut current = Compatible::<i32, Timeout>::from_current(Timeout(Some(1)));
+    if let Alt::Current(current) = current.as_either_mut() {
+        current.0 = Some(2);
+    }
+    assert_eq!(current.into_current(), Timeout(Some(2)));
+}
+
+#[test]
+pub fn test_serialize_seq_as_current() {
+    #[derive(Serialize)]
+    pub struct Batch {
+        #[serde(serialize_with = "serialize_seq_as_current")]
+        pub payloads: Vec<Compatible<String, Vec<u8>>>,
+    }
+
+    let batch = Batch {
+        payloads: vec![
+            Compatible::from_old("a".into()),
+            Compatible::from_current(vec![98]),
+        ],
+    };
+    assert_eq!(
+        serde_json::to_string(&batch).unwrap(),
+        r#"{"payloads":[[97],[98]]}"#
+    );
+}
+
+#[test]
+pub fn test_regroup_flat_fields() {
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    pub struct Meta {
+        pub x: i32,
+        pub y: i32,
+    }
+
+    // Flattening the new group into the old shape reads the loose top level fields into it
+    #[derive(Serialize, Deserialize)]
+    pub struct Old {
+        pub a: String,
+        pub b: bool,
+        #[serde(flatten)]
+        pub meta: Meta,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    pub struct New {
+        pub a: String,
+        pub b: bool,
+        pub meta: Meta,
+    }
+
+    impl From<Old> for New {
+        fn from(old: Old) -> Self {
+            New {
+                a: old.a,
+                b: old.b,
+                meta: old.meta,
+            }
+        }
+    }
+
+    let old: Compatible<Old, New> =
+        serde_json::from_str(r#"{"a":"p","b":true,"x":1,"y":2}"#).unwrap();
+    let current = serde_json::to_string(&old).unwrap();
+    assert_eq!(current, r#"{"a":"p","b":true,"meta":{"x":1,"y":2}}"#);
+
+    let current: Compatible<Old, New> = serde_json::from_str(&current).unwrap();
+    assert_eq!(
+        current.into_current(),
+        New {
+            a: "p".into(),
+            b: true,
+            meta: Meta { x: 1, y: 2 }
+        }
+    );
+}
+
+#[test]
+pub fn test_non_exhaustive_current() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[non_exhaustive]
+    pub struct Endpoint {
+        pub port: u16,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[non_exhaustive]
+    pub enum Mode {
+        Plain,
+        Tls { port: u16 },
+    }
+
+    impl From<u16> for Endpoint {
+        fn from(port: u16) -> Self {
+            Endpoint { port }
+        }
+    }
+
+    impl From<bool> for Mode {
+        fn from(old: bool) -> Self {
+            if old {
+                Mode::Tls { port: 443 }
+            } else {
+                Mode::Plain
+            }
+        }
+    }
+
+    let endpoints: Vec<Compatible<u16, Endpoint>> =
+        serde_json::from_str(r#"[80,{"port":8080}]"#).unwrap();
+    assert_eq!(
+        endpoints
+            .into_iter()
+            .map(Compatible::into_current)
+            .collect::<Vec<_>>(),
+        [Endpoint { port: 80 }, Endpoint { port: 8080 }]
+    );
+    let modes: Vec<Compatible<bool, Mode>> =
+        serde_json::from_str(r#"[true,"Plain",{"Tls":{"port":8443}}]"#).unwrap();
+    assert_eq!(
+        modes
+            .into_iter()
+            .map(Compatible::into_current)
+            .collect::<Vec<_>>(),
+        [
+            Mode::Tls { port: 443 },
+            Mode::Plain,
+            Mode::Tls { port: 8443 }
+        ]
+    );
+}
+
+#[test]
+pub fn test_retag() {
+    let from_text: Compatible<String, Vec<u8>> = Compatible::from_old("ab".into());
+    let from_boxed: Compatible<Box<[u8]>, Vec<u8>> = Compatible::from_old(Box::new([99]));
+    let normalized: Vec<Compatible<(), Vec<u8>>> = vec![
+        from_text.make_current().retag(),
+        from_boxed.make_current().retag(),
+        Compatible::<String, _>::from_current(vec![7]).retag(),
+    ];
+    assert!(normalized.iter().all(Compatible::is_current));
+    assert_eq!(
+        normalized
+            .into_iter()
+            .map(|value| value.into_result().unwrap())
+            .collect::<Vec<_>>(),
+        [vec![97, 98], vec![99], vec![7]]
+    );
+}
+
+#[test]
+pub fn test_recursive_boxed_enum() {
+    #[derive(Debug, Deserialize)]
+    pub enum OldExpr {
+        Num(i64),
+        Add(Box<OldExpr>, Box<OldExpr>),
+        Neg(Box<OldExpr>),
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    #[serde(tag = "op", rename_all = "lowercase")]
+    pub enum Expr {
+        Lit { value: i64 },
+        Sum { terms: Vec<Expr> },
+        Negate { child: Box<Expr> },
+    }
+
+    impl From<OldExpr> for Expr {
+        fn from(old: OldExpr) -> Self {
+            match old {
+                OldExpr::Num(value) => Expr::Lit { value },
+                OldExpr::Add(lhs, rhs) => Expr::Sum {
+                    terms: vec![(*lhs).into(), (*rhs).into()],
+                },
+                OldExpr::Neg(child) => Expr::Negate {
+                    child: Box::new((*child).into()),
+                },
+            }
+        }
+    }
+
+    let old: Compatible<OldExpr, Expr> =
+        serde_json::from_str(r#"{"Add":[{"Num":1},{"Neg":{"Add":[{"Num":2},{"Num":3}]}}]}"#)
+            .unwrap();
+    let lit = |value| Expr::Lit { value };
+    let expected = Expr::Sum {
+        terms: vec![
+            lit(1),
+            Expr::Negate {
+                child: Box::new(Expr::Sum {
+                    terms: vec![lit(2), lit(3)],
+                }),
+            },
+        ],
+    };
+    assert_eq!(old.into_current(), expected);
+
+    let current: Compatible<OldExpr, Expr> =
+        serde_json::from_str(&serde_json::to_string(&expected).unwrap()).unwrap();
+    assert!(current.is_current());
+    assert_eq!(current.into_current(), expected);
+}
+
+#[test]
+pub fn test_adjacently_tagged_payload() {
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    pub struct OldResize(u32);
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    pub struct Resize {
+        pub width: u32,
+        pub height: u32,
+    }
+
+    impl From<OldResize> for Resize {
+        fn from(old: OldResize) -> Self {
+            Resize {
+                width: old.0,
+                height: old.0,
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    #[serde(tag = "t", content = "c")]
+    pub enum Command {
+        Quit,
+        Resize(Compatible<OldResize, Resize>),
+    }
+
+    let resize =
+        |width, height| Command::Resize(Compatible::from_current(Resize { width, height }));
+    // The content coming before the tag makes serde buffer it before the untagged match
+    for input in [r#"{"t":"Resize","c":5}"#, r#"{"c":5,"t":"Resize"}"#] {
+        assert_eq!(
+            serde_json::from_str::<Command>(input).unwrap(),
+            resize(5, 5)
+        );
+    }
+    let current = r#"{"c":{"width":1,"height":2},"t":"Resize"}"#;
+    assert_eq!(
+        serde_json::from_str::<Command>(current).unwrap(),
+        resize(1, 2)
+    );
+    assert_eq!(
+        serde_json::to_string(&resize(1, 2)).unwrap(),
+        r#"{"t":"Resize","c":{"width":1,"height":2}}"#
+    );
+    assert_eq!(
+        serde_json::from_str::<Command>(r#"{"t":"Quit"}"#).unwrap(),
+        Command::Quit
+    );
+}
+
+#[test]
+pub fn test_into_current_boxed() {
+    pub trait Shape {
+        fn area(&self) -> f64;
+    }
+
+    #[derive(Deserialize)]
+    pub struct Rect {
+        pub width: f64,
+        pub height: f64,
+    }
+
+    impl Shape for Rect {
+        fn area(&self) -> f64 {
+            self.width * self.height
+        }
+    }
+
+    /// Squares used to be stored as their side length
+    #[derive(Deserialize)]
+    pub struct OldSquare(f64);
+
+    impl From<OldSquare> for Box<dyn Shape> {
+        fn from(old: OldSquare) -> Self {
+            Box::new(Rect {
+                width: old.0,
+                height: old.0,
+            })
+        }
+    }
+
+    impl From<OldSquare> for Rect {
+        fn from(old: OldSquare) -> Self {
+            Rect {
+                width: old.0,
+                height: old.0,
+            }
+        }
+    }
+
+    impl From<Rect> for Box<dyn Shape> {
+        fn from(current: Rect) -> Self {
+            Box::new(current)
+        }
+    }
+
+    let boxed = Compatible::<OldSquare, Box<dyn Shape>>::from_old(OldSquare(3.0));
+    assert_eq!(boxed.into_current_boxed::<dyn Shape>().area(), 9.0);
+
+    let shapes: Vec<Compatible<OldSquare, Rect>> =
+        serde_json::from_str(r#"[2.0,{"width":2.0,"height":5.0}]"#).unwrap();
+    let shapes: Vec<Box<dyn Shape>> = shapes
+        .into_iter()
+        .map(Compatible::into_current_boxed)
+        .collect();
+    assert_eq!(
+        shapes.iter().map(|shape| shape.area()).collect::<Vec<_>>(),
+        [4.0, 10.0]
+    );
+}
@@ -0,0 +1,88 @@
+use super::{Compatible, CompatibleWith};
+
+/// Layers one value over another, used by [`Compatible::coalesce_with`]  
+pub trait Merge {
+    /// Keeps what `self` sets and fills in the rest from `base`  
+    fn merge(self, base: Self) -> Self;
+}
+
+impl<T> Merge for Option<T> {
+    fn merge(self, base: Self) -> Self {
+        self.or(base)
+    }
+}
+
+impl<Old, Current> Compatible<Old, Current>
+where
+    Current: CompatibleWith<Old> + Merge,
+{
+    /// Converts and merges the result over `base`, so whatever old records never had comes
+    /// from a current template  
+    /// Already current values are merged over `base` the same way  
+    pub fn coalesce_with(self, base: Current) -> Current {
+        self.into_current().merge(base)
+    }
+}
+
+#[test]
+pub fn test_coalesce_with() {
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    pub struct Server {
+        pub host: Option<String>,
+        pub port: Option<u16>,
+        pub timeout_secs: Option<u64>,
+    }
+
+    impl Merge for Server {
+        fn merge(self, base: Self) -> Self {
+            Server {
+                host: self.host.merge(base.host),
+                port: self.port.merge(base.port),
+                timeout_secs: self.timeout_secs.merge(base.timeout_secs),
+            }
+        }
+    }
+
+    /// Old records only knew the address
+    impl From<(String, u16)> for Server {
+        fn from((host, port): (String, u16)) -> Self {
+            Server {
+                host: Some(host),
+                port: Some(port),
+                timeout_secs: None,
+            }
+        }
+    }
+
+    let template = || Server {
+        host: Some("localhost".into()),
+        port: Some(80),
+        timeout_secs: Some(30),
+    };
+    let old: Compatible<(String, u16), Server> =
+        serde_json::from_str(r#"["example.com",8080]"#).unwrap();
+    assert_eq!(
+        old.coalesce_with(template()),
+        Server {
+            host: Some("example.com".into()),
+            port: Some(8080),
+            timeout_secs: Some(30),
+        }
+    );
+
+    let current = Compatible::<(String, u16), _>::from_current(Server {
+        host: None,
+        port: Some(443),
+        timeout_secs: None,
+    });
+    assert_eq!(
+        current.coalesce_with(template()),
+        Server {
+            host: Some("localhost".into()),
+            port: Some(443),
+            timeout_secs: Some(30),
+        }
+    );
+}
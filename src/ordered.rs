@@ -0,0 +1,82 @@
+//! Insertion ordered maps and sets that used to be stored as lists, enabled with the `indexmap`
+//! feature  
+use super::Compatible;
+use core::hash::Hash;
+use indexmap::{IndexMap, IndexSet};
+use serde::{Deserialize, Serialize};
+
+/// An old list whose entries become the keys and values of an [`IndexMap`] or the members of an
+/// [`IndexSet`], in the order they were written  
+/// Neither `Vec<Old>` nor the map is local to your crate, so the orphan rule forbids a
+/// `From<Vec<Old>>` for it, this wrapper carries the conversion instead  
+/// Entries convert with `Old: Into<(K, V)>` for maps and `Old: Into<T>` for sets, a later
+/// duplicate key keeps the first position and takes the last value  
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct OrderedEntries<Old>(pub Vec<Old>);
+
+impl<Old, K, V> From<OrderedEntries<Old>> for IndexMap<K, V>
+where
+    Old: Into<(K, V)>,
+    K: Hash + Eq,
+{
+    fn from(old: OrderedEntries<Old>) -> Self {
+        old.0.into_iter().map(Into::into).collect()
+    }
+}
+
+impl<Old, T> From<OrderedEntries<Old>> for IndexSet<T>
+where
+    Old: Into<T>,
+    T: Hash + Eq,
+{
+    fn from(old: OrderedEntries<Old>) -> Self {
+        old.0.into_iter().map(Into::into).collect()
+    }
+}
+
+/// A map that used to be a list of `Old` entries  
+pub type CompatibleIndexMap<Old, K, V> = Compatible<OrderedEntries<Old>, IndexMap<K, V>>;
+
+/// A set that used to be a list of `Old` members  
+pub type CompatibleIndexSet<Old, T> = Compatible<OrderedEntries<Old>, IndexSet<T>>;
+
+#[test]
+pub fn test_index_map() {
+    #[derive(Deserialize)]
+    pub struct Entry {
+        pub key: String,
+        pub value: u32,
+    }
+
+    impl From<Entry> for (String, u32) {
+        fn from(old: Entry) -> Self {
+            (old.key, old.value)
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct Config {
+        pub limits: CompatibleIndexMap<Entry, String, u32>,
+    }
+
+    let old: Config =
+        serde_json::from_str(r#"{"limits":[{"key":"z","value":1},{"key":"a","value":2}]}"#)
+            .unwrap();
+    let limits = old.limits.into_current();
+    assert_eq!(
+        limits.iter().collect::<Vec<_>>(),
+        [(&"z".to_string(), &1), (&"a".to_string(), &2)]
+    );
+    assert_eq!(serde_json::to_string(&limits).unwrap(), r#"{"z":1,"a":2}"#);
+
+    let current: Config = serde_json::from_str(r#"{"limits":{"z":1,"a":2}}"#).unwrap();
+    assert_eq!(current.limits.into_current(), limits);
+
+    let tags: CompatibleIndexSet<String, String> =
+        serde_json::from_str(r#"["b","a","b"]"#).unwrap();
+    assert_eq!(
+        tags.into_current().into_iter().collect::<Vec<_>>(),
+        ["b", "a"]
+    );
+}
@@ -0,0 +1,33 @@
+//! Converting large batches on several threads, enabled with the `parallel` feature  
+use super::{Compatible, CompatibleWith};
+use rayon::prelude::*;
+
+/// Converts every value on rayon's global thread pool  
+/// The output keeps the input order  
+pub fn par_into_current<Old, Current>(values: Vec<Compatible<Old, Current>>) -> Vec<Current>
+where
+    Old: Send,
+    Current: CompatibleWith<Old> + Send,
+{
+    values
+        .into_par_iter()
+        .map(Compatible::into_current)
+        .collect()
+}
+
+#[test]
+pub fn test_par_into_current() {
+    let values = (0..10_000u32)
+        .map(|i| {
+            if i % 3 == 0 {
+                Compatible::<u16, u64>::from_old(i as u16)
+            } else {
+                Compatible::from_current(u64::from(i))
+            }
+        })
+        .collect::<Vec<_>>();
+    let expected = (0..10_000u64).collect::<Vec<_>>();
+    assert_eq!(par_into_current(values), expected);
+
+    assert!(par_into_current(Vec::<Compatible<u8, u32>>::new()).is_empty());
+}
@@ -0,0 +1,88 @@
+use super::Alt;
+use core::fmt::Display;
+use core::marker::PhantomData;
+use serde::{Deserialize, Serialize};
+
+/// A value that legacy data stored as a serialized `Result` (`{"Ok": ...}` / `{"Err": ...}`)
+/// and is now stored flat  
+/// Old `Ok` values are unwrapped, old `Err` values fail deserialization since there is no
+/// current value to produce for them  
+/// ```rust,ignore
+/// #[derive(Deserialize)]
+/// pub struct Reading {
+///     pub value: CompatibleResult<i32, String>,
+///     #[serde(deserialize_with = "CompatibleResult::<i32, String>::deserialize_with")]
+///     pub plain: i32,
+/// }
+/// ```
+#[derive(PartialEq, PartialOrd, Ord, Eq, Debug, Hash, Clone, Copy)]
+pub struct CompatibleResult<T, E>(T, PhantomData<fn() -> E>);
+
+impl<T, E> CompatibleResult<T, E> {
+    pub fn into_current(self) -> T {
+        self.0
+    }
+
+    pub fn deserialize_with<'de, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+        Alt<Result<T, E>, T>: Deserialize<'de>,
+        E: Display,
+    {
+        Self::deserialize(deserializer).map(Self::into_current)
+    }
+}
+
+impl<'de, T, E> Deserialize<'de> for CompatibleResult<T, E>
+where
+    Alt<Result<T, E>, T>: Deserialize<'de>,
+    E: Display,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        match Alt::deserialize(deserializer)? {
+            Alt::Old(Ok(value)) | Alt::Current(value) => Ok(CompatibleResult(value, PhantomData)),
+            Alt::Old(Err(err)) => Err(serde::de::Error::custom(format_args!(
+                "old value was stored as an error: {err}"
+            ))),
+        }
+    }
+}
+
+impl<T: Serialize, E> Serialize for CompatibleResult<T, E> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+#[test]
+pub fn test_compatible_result() {
+    use serde::*;
+    #[derive(Debug, Deserialize)]
+    pub struct Reading {
+        pub value: CompatibleResult<i32, String>,
+    }
+
+    let old: Reading = serde_json::from_str(r#"{"value":{"Ok":5}}"#).unwrap();
+    assert_eq!(old.value.into_current(), 5);
+
+    let current: Reading = serde_json::from_str(r#"{"value":5}"#).unwrap();
+    assert_eq!(serde_json::to_string(&current.value).unwrap(), "5");
+    assert_eq!(current.value.into_current(), 5);
+
+    let failed = serde_json::from_str::<Reading>(r#"{"value":{"Err":"sensor offline"}}"#);
+    assert!(failed
+        .unwrap_err()
+        .to_string()
+        .contains("old value was stored as an error: sensor offline"));
+
+    let plain = CompatibleResult::<i32, String>::deserialize_with(
+        &mut serde_json::Deserializer::from_str(r#"{"Ok":7}"#),
+    );
+    assert_eq!(plain.unwrap(), 7);
+}
@@ -0,0 +1,100 @@
+use super::{Compatible, CompatibleWith};
+use std::collections::HashSet;
+use std::hash::{BuildHasher, Hash};
+
+/// Collections that can drop elements in place, used by [`Compatible::retain_current`]  
+pub trait Retainable {
+    type Item;
+    fn retain_items<F>(&mut self, f: F)
+    where
+        F: FnMut(&Self::Item) -> bool;
+}
+
+impl<T> Retainable for Vec<T> {
+    type Item = T;
+    fn retain_items<F>(&mut self, f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.retain(f)
+    }
+}
+
+impl<T, S> Retainable for HashSet<T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+    type Item = T;
+    fn retain_items<F>(&mut self, f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.retain(f)
+    }
+}
+
+impl<Old, Current> Compatible<Old, Current>
+where
+    Current: CompatibleWith<Old> + Retainable + Default,
+{
+    /// Converts to the current collection and keeps only the elements matching `f`  
+    pub fn retain_current<F>(&mut self, f: F)
+    where
+        F: FnMut(&Current::Item) -> bool,
+    {
+        self.current_mut().retain_items(f)
+    }
+}
+
+#[test]
+pub fn test_retain_current() {
+    use serde::*;
+
+    #[derive(Deserialize)]
+    pub struct OldList(Vec<i32>);
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    pub struct New {
+        pub id: i32,
+        pub active: bool,
+    }
+
+    impl From<OldList> for Vec<New> {
+        fn from(old: OldList) -> Self {
+            old.0
+                .into_iter()
+                .map(|id| New {
+                    id,
+                    active: id % 2 == 0,
+                })
+                .collect()
+        }
+    }
+
+    impl From<OldList> for HashSet<i32> {
+        fn from(old: OldList) -> Self {
+            old.0.into_iter().collect()
+        }
+    }
+
+    let mut migrated: Compatible<OldList, Vec<New>> = serde_json::from_str("[1,2,3,4]").unwrap();
+    migrated.retain_current(|new| new.active);
+    assert_eq!(
+        migrated.into_current(),
+        vec![
+            New {
+                id: 2,
+                active: true
+            },
+            New {
+                id: 4,
+                active: true
+            }
+        ]
+    );
+
+    let mut ids: Compatible<OldList, HashSet<i32>> = Compatible::from_old(OldList(vec![1, 2, 3]));
+    ids.retain_current(|id| *id > 1);
+    assert_eq!(ids.into_current(), HashSet::from([2, 3]));
+}
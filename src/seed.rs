@@ -0,0 +1,88 @@
+use super::{Alt, Compatible};
+use core::marker::PhantomData;
+use serde::de::{DeserializeSeed, Deserializer};
+use serde::Deserialize;
+
+/// Like [`CompatibleWith`](crate::CompatibleWith) but the conversion gets a context, for
+/// migrations that need shared state such as an interner  
+pub trait CompatibleWithContext<Old, Ctx> {
+    fn from_old_with(value: Old, ctx: &mut Ctx) -> Self;
+}
+
+/// A [`DeserializeSeed`] that deserializes a [`Compatible`] and converts old values with the
+/// context it carries  
+/// The result is always current  
+pub struct CompatibleSeed<'a, Old, Current, Ctx> {
+    ctx: &'a mut Ctx,
+    marker: PhantomData<fn(Old) -> Current>,
+}
+
+impl<'a, Old, Current, Ctx> CompatibleSeed<'a, Old, Current, Ctx> {
+    pub fn new(ctx: &'a mut Ctx) -> Self {
+        CompatibleSeed {
+            ctx,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, Old, Current, Ctx> DeserializeSeed<'de> for CompatibleSeed<'_, Old, Current, Ctx>
+where
+    Current: CompatibleWithContext<Old, Ctx>,
+    Alt<Old, Current>: Deserialize<'de>,
+{
+    type Value = Compatible<Old, Current>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Alt::deserialize(deserializer)? {
+            Alt::Old(old) => {
+                super::counters::record();
+                Ok(Compatible::from_current(Current::from_old_with(
+                    old, self.ctx,
+                )))
+            }
+            Alt::Current(current) => Ok(Compatible::from_current(current)),
+        }
+    }
+}
+
+#[test]
+pub fn test_seed() {
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    pub struct Interner(HashMap<String, u32>);
+
+    impl Interner {
+        pub fn intern(&mut self, name: String) -> u32 {
+            let next = self.0.len() as u32;
+            *self.0.entry(name).or_insert(next)
+        }
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Symbol(u32);
+
+    impl CompatibleWithContext<String, Interner> for Symbol {
+        fn from_old_with(value: String, ctx: &mut Interner) -> Self {
+            Symbol(ctx.intern(value))
+        }
+    }
+
+    let mut interner = Interner::default();
+    let mut parse = |input: &str| {
+        CompatibleSeed::<String, Symbol, _>::new(&mut interner)
+            .deserialize(&mut serde_json::Deserializer::from_str(input))
+            .unwrap()
+            .into_result()
+    };
+
+    assert_eq!(parse(r#""a""#), Ok(Symbol(0)));
+    assert_eq!(parse(r#""b""#), Ok(Symbol(1)));
+    assert_eq!(parse(r#""a""#), Ok(Symbol(0)));
+    assert_eq!(parse("7"), Ok(Symbol(7)));
+    assert_eq!(interner.0.len(), 2);
+}
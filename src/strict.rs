@@ -0,0 +1,75 @@
+use super::{Alt, Compatible, CompatibleWith};
+use serde::{Deserialize, Serialize};
+
+/// A [`Compatible`] that refuses to serialize while it still holds the old variant  
+/// Writing legacy data back out is almost always a forgotten `make_current`, so this turns it
+/// into a serialization error instead  
+#[derive(PartialEq, PartialOrd, Ord, Eq, Debug, Hash, Clone, Copy)]
+pub struct StrictCompatible<Old, Current>(Compatible<Old, Current>);
+
+impl<Old, Current> StrictCompatible<Old, Current> {
+    pub fn new(value: Compatible<Old, Current>) -> Self {
+        StrictCompatible(value)
+    }
+
+    pub fn into_inner(self) -> Compatible<Old, Current> {
+        self.0
+    }
+}
+
+impl<Old, Current> StrictCompatible<Old, Current>
+where
+    Current: CompatibleWith<Old>,
+{
+    pub fn into_current(self) -> Current {
+        self.0.into_current()
+    }
+
+    pub fn make_current(self) -> Self {
+        StrictCompatible(self.0.make_current())
+    }
+}
+
+impl<Old, Current> From<Compatible<Old, Current>> for StrictCompatible<Old, Current> {
+    fn from(value: Compatible<Old, Current>) -> Self {
+        StrictCompatible(value)
+    }
+}
+
+impl<'de, Old, Current> Deserialize<'de> for StrictCompatible<Old, Current>
+where
+    Compatible<Old, Current>: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        Compatible::deserialize(deserializer).map(StrictCompatible)
+    }
+}
+
+impl<Old, Current: Serialize> Serialize for StrictCompatible<Old, Current> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        match (self.0).0 {
+            Alt::Old(_) => Err(serde::ser::Error::custom(
+                "refusing to serialize a value still in the old format, call make_current first",
+            )),
+            Alt::Current(ref current) => current.serialize(serializer),
+        }
+    }
+}
+
+#[test]
+pub fn test_strict() {
+    let old = StrictCompatible::new(Compatible::<u8, u32>::from_old(1));
+    let err = serde_json::to_string(&old).unwrap_err();
+    assert!(err.to_string().contains("call make_current first"));
+
+    assert_eq!(serde_json::to_string(&old.make_current()).unwrap(), "1");
+
+    let parsed: StrictCompatible<u8, u32> = serde_json::from_str("2").unwrap();
+    assert_eq!(serde_json::to_string(&parsed).unwrap(), "2");
+}
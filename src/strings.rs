@@ -0,0 +1,51 @@
+//! String fields that moved to a compact string type, enabled with the `compact_str` and
+//! `smol_str` features  
+//! Both crates implement `From<String>`, so the blanket [`CompatibleWith`](crate::CompatibleWith)
+//! impl converts an old `String` without any glue, and a long string keeps its heap buffer  
+//! Old and current data are both JSON strings, so every value takes the old path and is
+//! converted, the result is the same either way  
+use super::Compatible;
+
+/// A field that used to be a `String`, or another `Old` that `CompactString` converts from  
+#[cfg(feature = "compact_str")]
+pub type CompatibleCompactString<Old = String> = Compatible<Old, compact_str::CompactString>;
+
+/// A field that used to be a `String`, or another `Old` that `SmolStr` converts from  
+#[cfg(feature = "smol_str")]
+pub type CompatibleSmolStr<Old = String> = Compatible<Old, smol_str::SmolStr>;
+
+#[cfg(feature = "compact_str")]
+#[test]
+pub fn test_compact_string() {
+    use compact_str::CompactString;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Serialize)]
+    pub struct Tag {
+        pub name: CompatibleCompactString,
+    }
+
+    let tag: Tag = serde_json::from_str(r#"{"name":"a"}"#).unwrap();
+    assert_eq!(serde_json::to_string(&tag).unwrap(), r#"{"name":"a"}"#);
+    assert_eq!(tag.name.into_current(), CompactString::from("a"));
+
+    let long = "a string too long to be stored inline";
+    let name = <CompatibleCompactString>::from_old(long.to_string()).into_current();
+    assert!(name.is_heap_allocated());
+    assert_eq!(name, long);
+}
+
+#[cfg(feature = "smol_str")]
+#[test]
+pub fn test_smol_str() {
+    use serde::Deserialize;
+    use smol_str::SmolStr;
+
+    #[derive(Deserialize)]
+    pub struct Tag {
+        pub name: CompatibleSmolStr,
+    }
+
+    let tag: Tag = serde_json::from_str(r#"{"name":"a"}"#).unwrap();
+    assert_eq!(tag.name.into_current(), SmolStr::new("a"));
+}
@@ -0,0 +1,155 @@
+//! Helpers for testing your own migrations, enabled with the `test-util` feature  
+use super::{Compatible, CompatibleWith};
+use approx::AbsDiffEq;
+use core::fmt::Debug;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+/// Converts `value` and panics unless it is within `epsilon` of `expected` according to
+/// [`approx::AbsDiffEq`]  
+/// Widening `f32` to `f64` keeps the `f32` rounding error (`0.1f32` becomes
+/// `0.10000000149011612`), so exact comparisons against `f64` literals fail  
+#[track_caller]
+pub fn assert_current_approx_eq<Old, Current>(
+    value: Compatible<Old, Current>,
+    expected: &Current,
+    epsilon: Current::Epsilon,
+) where
+    Current: CompatibleWith<Old> + AbsDiffEq + Debug,
+    Current::Epsilon: Clone + Debug,
+{
+    let current = value.into_current();
+    assert!(
+        current.abs_diff_eq(expected, epsilon.clone()),
+        "converted value {current:?} is not within {epsilon:?} of {expected:?}"
+    );
+}
+
+/// Reads `old_json` through `Compatible<Old, Current>`, converts it and panics unless the
+/// current value serializes to the same JSON as `expected_current_json`  
+/// Both sides are compared as parsed values, so whitespace and key order don't matter  
+#[track_caller]
+pub fn assert_roundtrip<Old, Current>(old_json: &str, expected_current_json: &str)
+where
+    Old: DeserializeOwned,
+    Current: DeserializeOwned + CompatibleWith<Old> + Serialize,
+{
+    let value: Compatible<Old, Current> = match serde_json::from_str(old_json) {
+        Ok(value) => value,
+        Err(err) => panic!("{old_json} did not deserialize in either shape: {err}"),
+    };
+    let current = serde_json::to_value(value.into_current()).expect("current value serializes");
+    let expected: Value =
+        serde_json::from_str(expected_current_json).expect("expected JSON is valid");
+    assert!(
+        current == expected,
+        "{old_json} migrated to {current} instead of {expected}"
+    );
+}
+
+#[test]
+pub fn test_approx_eq() {
+    use serde::*;
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Old {
+        pub reading: f32,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    pub struct New {
+        pub reading: f64,
+        pub scale: f64,
+    }
+
+    impl From<Old> for New {
+        fn from(old: Old) -> Self {
+            New {
+                reading: old.reading.into(),
+                scale: 1.0,
+            }
+        }
+    }
+
+    impl AbsDiffEq for New {
+        type Epsilon = f64;
+
+        fn default_epsilon() -> f64 {
+            f64::default_epsilon()
+        }
+
+        fn abs_diff_eq(&self, other: &Self, epsilon: f64) -> bool {
+            self.reading.abs_diff_eq(&other.reading, epsilon)
+                && self.scale.abs_diff_eq(&other.scale, epsilon)
+        }
+    }
+
+    let expected = New {
+        reading: 0.1,
+        scale: 1.0,
+    };
+    let migrated: Compatible<Old, New> = serde_json::from_str(r#"{"reading":0.1}"#).unwrap();
+    assert_ne!(f64::from(0.1f32), expected.reading);
+    assert_current_approx_eq(migrated, &expected, 1e-6);
+
+    // approx's own impls cover plain floats
+    assert_current_approx_eq(Compatible::<f32, f64>::from_old(0.1), &0.1, 1e-6);
+}
+
+#[test]
+pub fn test_assert_roundtrip() {
+    use serde::*;
+    #[derive(Deserialize)]
+    pub struct Old {
+        pub name: String,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct New {
+        pub names: Vec<String>,
+        pub primary: usize,
+    }
+
+    impl From<Old> for New {
+        fn from(old: Old) -> Self {
+            New {
+                names: vec![old.name],
+                primary: 0,
+            }
+        }
+    }
+
+    assert_roundtrip::<Old, New>(r#"{"name":"a"}"#, r#"{ "primary": 0, "names": ["a"] }"#);
+    assert_roundtrip::<Old, New>(
+        r#"{"names":["a","b"],"primary":1}"#,
+        r#"{"names":["a","b"],"primary":1}"#,
+    );
+    assert_roundtrip::<String, Vec<u8>>(r#""ab""#, "[97,98]");
+}
+
+#[test]
+#[should_panic(expected = r#"{"name":"a"} migrated to {"names":["a"],"primary":0} instead of"#)]
+pub fn test_assert_roundtrip_mismatch() {
+    use serde::*;
+    #[derive(Deserialize)]
+    pub struct Old {
+        pub name: String,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct New {
+        pub names: Vec<String>,
+        pub primary: usize,
+    }
+
+    impl From<Old> for New {
+        fn from(old: Old) -> Self {
+            New {
+                names: vec![old.name],
+                primary: 0,
+            }
+        }
+    }
+
+    assert_roundtrip::<Old, New>(r#"{"name":"a"}"#, r#"{"names":["b"],"primary":0}"#);
+}
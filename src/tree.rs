@@ -0,0 +1,137 @@
+use core::fmt;
+
+/// Returned by [`convert_tree`] when the old tree is nested deeper than allowed  
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Copy)]
+pub struct DepthLimitExceeded {
+    pub limit: usize,
+}
+
+impl fmt::Display for DepthLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "old tree is nested deeper than the limit of {}",
+            self.limit
+        )
+    }
+}
+
+impl std::error::Error for DepthLimitExceeded {}
+
+struct Frame<Old, Node, Current> {
+    node: Node,
+    children: std::vec::IntoIter<Old>,
+    done: Vec<Current>,
+}
+
+/// Converts a recursive old tree with an explicit stack instead of recursing in `From<Old>`,
+/// so deeply nested legacy data returns an error instead of overflowing the stack  
+/// `split` takes an old node apart into its own data and its children, `join` builds the
+/// current node from that data and the already converted children  
+/// ```rust,ignore
+/// impl From<OldDir> for DirNode { .. } // recursive, fine for trusted data
+///
+/// let root = convert_tree(
+///     old_root,
+///     64,
+///     |old: OldDir| ((old.id, old.name), old.children),
+///     |(id, name), children| DirNode { id, name, children },
+/// )?;
+/// ```
+pub fn convert_tree<Old, Node, Current>(
+    root: Old,
+    max_depth: usize,
+    mut split: impl FnMut(Old) -> (Node, Vec<Old>),
+    mut join: impl FnMut(Node, Vec<Current>) -> Current,
+) -> Result<Current, DepthLimitExceeded> {
+    let mut stack = Vec::new();
+    let (node, children) = split(root);
+    stack.push(Frame {
+        node,
+        children: children.into_iter(),
+        done: Vec::new(),
+    });
+
+    while let Some(frame) = stack.last_mut() {
+        if let Some(child) = frame.children.next() {
+            if stack.len() >= max_depth {
+                // Take the rest apart here as well, dropping it would recurse just the same
+                let mut pending = vec![child];
+                pending.extend(stack.into_iter().flat_map(|frame| frame.children));
+                while let Some(old) = pending.pop() {
+                    pending.extend(split(old).1);
+                }
+                return Err(DepthLimitExceeded { limit: max_depth });
+            }
+            let (node, children) = split(child);
+            stack.push(Frame {
+                node,
+                children: children.into_iter(),
+                done: Vec::new(),
+            });
+        } else if let Some(frame) = stack.pop() {
+            let current = join(frame.node, frame.done);
+            match stack.last_mut() {
+                Some(parent) => parent.done.push(current),
+                None => return Ok(current),
+            }
+        }
+    }
+    unreachable!("the root frame returns before the stack empties")
+}
+
+#[test]
+pub fn test_convert_tree() {
+    pub struct OldDir {
+        pub name: String,
+        pub children: Vec<OldDir>,
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub struct Dir {
+        pub name: String,
+        pub depth: usize,
+        pub children: Vec<Dir>,
+    }
+
+    fn nested(depth: usize) -> OldDir {
+        let mut dir = OldDir {
+            name: "leaf".into(),
+            children: vec![],
+        };
+        for _ in 1..depth {
+            dir = OldDir {
+                name: "dir".into(),
+                children: vec![dir],
+            };
+        }
+        dir
+    }
+
+    fn convert(old: OldDir, max_depth: usize) -> Result<Dir, DepthLimitExceeded> {
+        convert_tree(
+            old,
+            max_depth,
+            |old| (old.name, old.children),
+            |name, children: Vec<Dir>| Dir {
+                name,
+                depth: children.first().map_or(1, |child| child.depth + 1),
+                children,
+            },
+        )
+    }
+
+    let small = OldDir {
+        name: "root".into(),
+        children: vec![nested(2), nested(1)],
+    };
+    let small = convert(small, 8).unwrap();
+    assert_eq!(small.depth, 3);
+    assert_eq!(small.children[1].name, "leaf");
+
+    assert_eq!(convert(nested(500), 500).unwrap().depth, 500);
+    assert_eq!(
+        convert(nested(200_000), 1_000).unwrap_err(),
+        DepthLimitExceeded { limit: 1_000 }
+    );
+}
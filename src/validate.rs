@@ -0,0 +1,71 @@
+use super::{Alt, Compatible, CompatibleWith};
+use core::fmt::Display;
+use serde::Serialize;
+
+/// Checks a current value before it is written out by [`Compatible::try_serialize_current`]  
+pub trait Validate {
+    type Error: Display;
+    fn validate(&self) -> Result<(), Self::Error>;
+}
+
+impl<Old, Current> Compatible<Old, Current> {
+    /// Serializes the current shape, cloning and converting an old value, but only once it
+    /// passes [`Validate`], otherwise the validation error is returned as a serde error  
+    /// Usable as `#[serde(serialize_with = "Compatible::try_serialize_current")]`  
+    pub fn try_serialize_current<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+        Old: Clone,
+        Current: Serialize + CompatibleWith<Old> + Validate,
+    {
+        let converted;
+        let current = match self.0 {
+            Alt::Old(ref old) => {
+                converted = super::upgrade::<_, Current>(old.clone());
+                &converted
+            }
+            Alt::Current(ref current) => current,
+        };
+        current.validate().map_err(serde::ser::Error::custom)?;
+        current.serialize(serializer)
+    }
+}
+
+#[test]
+pub fn test_try_serialize_current() {
+    #[derive(Serialize)]
+    pub struct Percent(u8);
+
+    impl From<u32> for Percent {
+        fn from(old: u32) -> Self {
+            Percent(old.min(255) as u8)
+        }
+    }
+
+    impl Validate for Percent {
+        type Error = String;
+        fn validate(&self) -> Result<(), String> {
+            match self.0 {
+                0..=100 => Ok(()),
+                value => Err(format!("{value} is not a percentage")),
+            }
+        }
+    }
+
+    #[derive(Serialize)]
+    pub struct Progress {
+        #[serde(serialize_with = "Compatible::try_serialize_current")]
+        pub done: Compatible<u32, Percent>,
+    }
+
+    let valid = Progress {
+        done: Compatible::from_old(40),
+    };
+    assert_eq!(serde_json::to_string(&valid).unwrap(), r#"{"done":40}"#);
+
+    let invalid = Progress {
+        done: Compatible::from_old(400),
+    };
+    let err = serde_json::to_string(&invalid).unwrap_err();
+    assert!(err.to_string().contains("255 is not a percentage"));
+}
@@ -0,0 +1,215 @@
+use super::{Alt, Compatible, CompatibleWith};
+use core::fmt;
+use core::marker::PhantomData;
+use serde::de::{Error, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize};
+
+/// A schema version number for a type, usually from `#[derive(Versioned)]` with
+/// `#[compatible(version = 2)]`  
+pub trait Versioned {
+    const VERSION: u32;
+}
+
+/// A [`Compatible`] serialized inside a `{"version": 2, "data": ...}` envelope  
+/// The version number decides which shape `data` is read as, instead of the untagged match  
+/// Versions newer than `Current::VERSION` are rejected as unsupported  
+/// `version` has to come before `data` since the payload isn't buffered  
+/// Formats that aren't self-describing, like postcard or bincode, write the envelope as a
+/// `(version, data)` sequence and read it back the same way  
+#[derive(PartialEq, PartialOrd, Ord, Eq, Debug, Hash, Clone, Copy)]
+pub struct VersionedCompatible<Old, Current>(Compatible<Old, Current>);
+
+impl<Old, Current> VersionedCompatible<Old, Current> {
+    pub fn new(value: Compatible<Old, Current>) -> Self {
+        VersionedCompatible(value)
+    }
+
+    pub fn into_inner(self) -> Compatible<Old, Current> {
+        self.0
+    }
+}
+
+impl<Old, Current> VersionedCompatible<Old, Current>
+where
+    Current: CompatibleWith<Old>,
+{
+    pub fn into_current(self) -> Current {
+        self.0.into_current()
+    }
+}
+
+impl<Old, Current> Serialize for VersionedCompatible<Old, Current>
+where
+    Old: Versioned + Serialize,
+    Current: Versioned + Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        let mut envelope = serializer.serialize_struct("VersionedCompatible", 2)?;
+        match (self.0).0 {
+            Alt::Old(ref old) => {
+                envelope.serialize_field("version", &Old::VERSION)?;
+                envelope.serialize_field("data", old)?;
+            }
+            Alt::Current(ref current) => {
+                envelope.serialize_field("version", &Current::VERSION)?;
+                envelope.serialize_field("data", current)?;
+            }
+        }
+        envelope.end()
+    }
+}
+
+struct EnvelopeVisitor<Old, Current>(PhantomData<fn() -> (Old, Current)>);
+
+/// Rejects versions newer than `Current` and ones that belong to neither shape  
+fn check_version<Old, Current, E>(version: u32) -> Result<(), E>
+where
+    Old: Versioned,
+    Current: Versioned,
+    E: Error,
+{
+    if version > Current::VERSION {
+        return Err(E::custom(format_args!(
+            "unsupported version {version}, the newest known version is {}",
+            Current::VERSION
+        )));
+    }
+    if version != Current::VERSION && version != Old::VERSION {
+        return Err(E::custom(format_args!("unknown version {version}")));
+    }
+    Ok(())
+}
+
+impl<'de, Old, Current> Visitor<'de> for EnvelopeVisitor<Old, Current>
+where
+    Old: Versioned + Deserialize<'de>,
+    Current: Versioned + Deserialize<'de> + CompatibleWith<Old>,
+{
+    type Value = VersionedCompatible<Old, Current>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a `version` followed by `data`")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        if map.next_key::<String>()?.as_deref() != Some("version") {
+            return Err(A::Error::custom("expected `version` as the first key"));
+        }
+        let version: u32 = map.next_value()?;
+        check_version::<Old, Current, A::Error>(version)?;
+
+        if map.next_key::<String>()?.as_deref() != Some("data") {
+            return Err(A::Error::missing_field("data"));
+        }
+        let compatible = if version == Current::VERSION {
+            Compatible(Alt::Current(map.next_value()?))
+        } else {
+            Compatible(Alt::Old(map.next_value()?))
+        };
+        while map.next_key::<IgnoredAny>()?.is_some() {
+            map.next_value::<IgnoredAny>()?;
+        }
+
+        Ok(VersionedCompatible(compatible.make_current()))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let version: u32 = seq
+            .next_element()?
+            .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+        check_version::<Old, Current, A::Error>(version)?;
+
+        let compatible = if version == Current::VERSION {
+            seq.next_element()?
+                .map(|current| Compatible(Alt::Current(current)))
+        } else {
+            seq.next_element()?.map(|old| Compatible(Alt::Old(old)))
+        };
+        let compatible = compatible.ok_or_else(|| A::Error::invalid_length(1, &self))?;
+
+        Ok(VersionedCompatible(compatible.make_current()))
+    }
+}
+
+impl<'de, Old, Current> Deserialize<'de> for VersionedCompatible<Old, Current>
+where
+    Old: Versioned + Deserialize<'de>,
+    Current: Versioned + Deserialize<'de> + CompatibleWith<Old>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        deserializer.deserialize_struct(
+            "VersionedCompatible",
+            &["version", "data"],
+            EnvelopeVisitor(PhantomData),
+        )
+    }
+}
+
+#[test]
+pub fn test_versioned() {
+    use serde::*;
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct Old(String);
+
+    impl Versioned for Old {
+        const VERSION: u32 = 1;
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    pub struct New(String);
+
+    impl Versioned for New {
+        const VERSION: u32 = 2;
+    }
+
+    impl From<Old> for New {
+        fn from(old: Old) -> Self {
+            New(old.0.to_uppercase())
+        }
+    }
+
+    type Value = VersionedCompatible<Old, New>;
+
+    // Both shapes are strings, the tag alone decides that version 1 data is read as `Old`
+    let old: Value = serde_json::from_str(r#"{"version":1,"data":"a"}"#).unwrap();
+    assert_eq!(
+        serde_json::to_string(&old).unwrap(),
+        r#"{"version":2,"data":"A"}"#
+    );
+    assert_eq!(old.into_current(), New("A".into()));
+
+    // The untagged match would have sent this through `Old` as well
+    let current: Value = serde_json::from_str(r#"{"version":2,"data":"a"}"#).unwrap();
+    assert_eq!(current.into_current(), New("a".into()));
+
+    let err = serde_json::from_str::<Value>(r#"{"version":0,"data":"a"}"#).unwrap_err();
+    assert!(err.to_string().contains("unknown version 0"));
+
+    // postcard writes the envelope as a plain `(version, data)` sequence
+    let old = VersionedCompatible::new(Compatible::<Old, New>::from_old(Old("b".into())));
+    let bytes = postcard::to_allocvec(&old).unwrap();
+    assert_eq!(bytes, [1, 1, b'b']);
+    let old: Value = postcard::from_bytes(&bytes).unwrap();
+    assert_eq!(old.into_current(), New("B".into()));
+
+    let current = VersionedCompatible::new(Compatible::<Old, New>::from_current(New("b".into())));
+    let bytes = postcard::to_allocvec(&current).unwrap();
+    assert_eq!(bytes, [2, 1, b'b']);
+    let current: Value = postcard::from_bytes(&bytes).unwrap();
+    assert_eq!(current.into_current(), New("b".into()));
+
+    assert!(postcard::from_bytes::<Value>(&[3, 1, b'b']).is_err());
+}
@@ -0,0 +1,43 @@
+#![cfg(feature = "counters")]
+use compatible_with::{conversion_count, reset_conversion_count, Compatible, Transparent};
+
+// The counter is global, keep this the only test in the binary so nothing else converts
+#[test]
+pub fn test_conversion_count() {
+    reset_conversion_count();
+    let values: Vec<Compatible<u8, u32>> = serde_json::from_str("[1,2,3]").unwrap();
+    assert_eq!(conversion_count(), 3);
+
+    let currents = values
+        .into_iter()
+        .map(Compatible::into_current)
+        .collect::<Vec<_>>();
+    assert_eq!(currents, [1, 2, 3]);
+    assert_eq!(conversion_count(), 3);
+
+    Compatible::<u8, u32>::from_old(4).into_current();
+    Compatible::<u8, u32>::from_current(5).into_current();
+    assert_eq!(conversion_count(), 4);
+
+    // The converted copy behind the diff counts too
+    #[cfg(feature = "json")]
+    {
+        Compatible::<u8, u32>::from_old(6)
+            .diff_against_old()
+            .unwrap();
+        assert_eq!(conversion_count(), 5);
+    }
+
+    reset_conversion_count();
+    assert_eq!(conversion_count(), 0);
+
+    // The identity conversion still counts through the plain impl, the transparent helpers
+    // skip it
+    let _: Transparent<u8> = serde_json::from_str("1").unwrap();
+    assert_eq!(conversion_count(), 1);
+    let value =
+        Transparent::<u8>::deserialize_transparent(&mut serde_json::Deserializer::from_str("2"))
+            .unwrap();
+    Transparent::from_old(value).into_inner();
+    assert_eq!(conversion_count(), 1);
+}
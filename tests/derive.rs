@@ -34,3 +34,436 @@ pub fn test_derived() {
 
     assert_eq!(migrated.a, MyType("1".into()));
 }
+
+#[test]
+pub fn test_compatible_fields() {
+    use compatible_with::CompatibleFields;
+    use serde::*;
+
+    #[derive(Deserialize)]
+    pub struct OldName(String);
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    pub struct Name {
+        pub first: String,
+        pub last: String,
+    }
+
+    impl From<OldName> for Name {
+        fn from(old: OldName) -> Self {
+            let (first, last) = old.0.split_once(' ').unwrap_or((&old.0, ""));
+            Name {
+                first: first.into(),
+                last: last.into(),
+            }
+        }
+    }
+
+    /// Ages used to be stored as strings
+    #[derive(Deserialize)]
+    pub struct OldAge(String);
+
+    impl From<OldAge> for u32 {
+        fn from(old: OldAge) -> Self {
+            old.0.parse().unwrap_or_default()
+        }
+    }
+
+    #[derive(Debug, PartialEq, CompatibleFields)]
+    #[serde(deny_unknown_fields)]
+    pub struct User {
+        #[compatible(old = "OldName")]
+        pub name: Name,
+        #[compatible(old = "OldAge")]
+        #[serde(rename = "years")]
+        pub age: u32,
+        pub id: i64,
+    }
+
+    let old: User = serde_json::from_str(r#"{"name":"Jane Doe","years":"30","id":1}"#).unwrap();
+    assert_eq!(
+        old,
+        User {
+            name: Name {
+                first: "Jane".into(),
+                last: "Doe".into()
+            },
+            age: 30,
+            id: 1
+        }
+    );
+    assert_eq!(
+        serde_json::to_string(&old).unwrap(),
+        r#"{"name":{"first":"Jane","last":"Doe"},"years":30,"id":1}"#
+    );
+
+    let current: User = serde_json::from_str(&serde_json::to_string(&old).unwrap()).unwrap();
+    assert_eq!(current, old);
+}
+
+#[test]
+pub fn test_versioned() {
+    use compatible_with::{Versioned, VersionedCompatible};
+    use serde::*;
+
+    #[derive(Debug, Deserialize, Versioned)]
+    #[compatible(version = 1)]
+    pub struct Old {
+        pub a: i32,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Versioned)]
+    #[compatible(version = 2)]
+    pub struct New {
+        pub a: String,
+    }
+
+    impl From<Old> for New {
+        fn from(old: Old) -> Self {
+            New {
+                a: old.a.to_string(),
+            }
+        }
+    }
+
+    assert_eq!(<New as Versioned>::VERSION, 2);
+
+    let old: VersionedCompatible<Old, New> =
+        serde_json::from_str(r#"{"version":1,"data":{"a":1}}"#).unwrap();
+    assert_eq!(old.into_current(), New { a: "1".into() });
+
+    let err = serde_json::from_str::<VersionedCompatible<Old, New>>(
+        r#"{"version":3,"data":{"a":"1","b":2}}"#,
+    )
+    .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("unsupported version 3, the newest known version is 2"));
+}
+
+#[test]
+pub fn test_derived_validate() {
+    use serde::*;
+
+    #[derive(Debug, Deserialize, PartialEq, CompatibleWith)]
+    #[serde(try_from = "Compatible::<i32, Port>")]
+    #[compatible(validate = "Port::validate")]
+    pub struct Port(u16);
+
+    impl Port {
+        pub fn validate(&self) -> Result<(), &'static str> {
+            if self.0 == 0 {
+                Err("port 0 is reserved")
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    impl From<i32> for Port {
+        fn from(value: i32) -> Self {
+            Port(u16::try_from(value).unwrap_or_default())
+        }
+    }
+
+    assert_eq!(serde_json::from_str::<Port>("8080").unwrap(), Port(8080));
+
+    let err = serde_json::from_str::<Port>("-1").unwrap_err();
+    assert!(err.to_string().contains("port 0 is reserved"));
+}
+
+#[test]
+pub fn test_compatible_fields_enum() {
+    use compatible_with::CompatibleFields;
+
+    #[derive(Debug, PartialEq, CompatibleFields)]
+    #[compatible(variant_rename_all = "camelCase")]
+    pub enum Command {
+        Move { target_x: i32, target_y: i32 },
+        Say(String),
+        Stop,
+    }
+
+    let old: Vec<Command> =
+        serde_json::from_str(r#"[{"Move":{"target_x":1,"target_y":2}},{"Say":"hi"},"Stop"]"#)
+            .unwrap();
+    assert_eq!(
+        old,
+        [
+            Command::Move {
+                target_x: 1,
+                target_y: 2
+            },
+            Command::Say("hi".into()),
+            Command::Stop
+        ]
+    );
+    assert_eq!(
+        serde_json::to_string(&old).unwrap(),
+        r#"[{"Move":{"targetX":1,"targetY":2}},{"Say":"hi"},"Stop"]"#
+    );
+
+    let current: Vec<Command> =
+        serde_json::from_str(&serde_json::to_string(&old).unwrap()).unwrap();
+    assert_eq!(current, old);
+}
+
+#[test]
+pub fn test_compatible_fields_aliases() {
+    use compatible_with::CompatibleFields;
+
+    #[derive(Debug, PartialEq, CompatibleFields)]
+    pub struct Server {
+        #[compatible(aliases("host", "hostname"))]
+        pub address: String,
+        pub port: u16,
+    }
+
+    for key in ["address", "host", "hostname"] {
+        let server: Server =
+            serde_json::from_str(&format!(r#"{{"{key}":"example.com","port":80}}"#)).unwrap();
+        assert_eq!(
+            server,
+            Server {
+                address: "example.com".into(),
+                port: 80
+            }
+        );
+        assert_eq!(
+            serde_json::to_string(&server).unwrap(),
+            r#"{"address":"example.com","port":80}"#
+        );
+    }
+}
+
+#[test]
+pub fn test_compatible_fields_tuple() {
+    use compatible_with::CompatibleFields;
+
+    #[derive(Debug, PartialEq, CompatibleFields)]
+    pub struct Version(#[compatible(old = "String")] Vec<u8>, bool);
+
+    let old: Version = serde_json::from_str(r#"["ab",true]"#).unwrap();
+    assert_eq!(old, Version(b"ab".to_vec(), true));
+    assert_eq!(serde_json::to_string(&old).unwrap(), "[[97,98],true]");
+
+    let current: Version = serde_json::from_str("[[97,98],true]").unwrap();
+    assert_eq!(current, old);
+}
+
+#[test]
+pub fn test_derived_generic_marker() {
+    use core::marker::PhantomData;
+    use serde::*;
+
+    #[derive(Debug, PartialEq)]
+    pub struct Meters;
+
+    #[derive(Debug, Deserialize, PartialEq, CompatibleWith)]
+    #[serde(from = "Compatible::<f32, Length<U>>")]
+    pub struct Length<U> {
+        pub value: f64,
+        pub unit: PhantomData<U>,
+    }
+
+    impl<U> From<f32> for Length<U> {
+        fn from(old: f32) -> Self {
+            Length {
+                value: old.into(),
+                unit: PhantomData,
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct Track {
+        pub length: Length<Meters>,
+    }
+
+    let old: Track = serde_json::from_str(r#"{"length":1.5}"#).unwrap();
+    assert_eq!(
+        old.length,
+        Length {
+            value: 1.5,
+            unit: PhantomData
+        }
+    );
+}
+
+#[test]
+pub fn test_compatible_with_attribute() {
+    use serde::*;
+
+    #[derive(Deserialize)]
+    pub struct OldUser {
+        pub name: String,
+    }
+
+    #[compatible_with::compatible_with(old = "OldUser")]
+    #[derive(Debug, Serialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    pub struct User {
+        #[serde(rename = "full_name")]
+        pub name: String,
+        pub admin: bool,
+    }
+
+    impl From<OldUser> for User {
+        fn from(old: OldUser) -> Self {
+            User {
+                name: old.name,
+                admin: false,
+            }
+        }
+    }
+
+    let old: User = serde_json::from_str(r#"{"name":"a"}"#).unwrap();
+    assert_eq!(
+        old,
+        User {
+            name: "a".into(),
+            admin: false
+        }
+    );
+    assert_eq!(
+        serde_json::to_string(&old).unwrap(),
+        r#"{"full_name":"a","admin":false}"#
+    );
+
+    let current: User = serde_json::from_str(r#"{"full_name":"b","admin":true}"#).unwrap();
+    assert_eq!(
+        current,
+        User {
+            name: "b".into(),
+            admin: true
+        }
+    );
+    assert_eq!(
+        User::from(Compatible::from_old(OldUser { name: "c".into() })).name,
+        "c"
+    );
+}
+
+#[test]
+pub fn test_serde_bytes_fields() {
+    use compatible_with::CompatibleFields;
+    use serde::*;
+
+    #[derive(Deserialize)]
+    pub struct OldKey {
+        #[serde(with = "serde_bytes")]
+        pub key: Vec<u8>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    pub struct Key {
+        pub hex: String,
+    }
+
+    impl From<OldKey> for Key {
+        fn from(old: OldKey) -> Self {
+            Key {
+                hex: old.key.iter().map(|b| format!("{b:02x}")).collect(),
+            }
+        }
+    }
+
+    let key: Compatible<OldKey, Key> = serde_json::from_str(r#"{"key":[1,171]}"#).unwrap();
+    assert_eq!(key.into_current(), Key { hex: "01ab".into() });
+
+    #[derive(Debug, PartialEq, CompatibleFields)]
+    pub struct Packet {
+        /// Payloads used to be text
+        #[compatible(old = "String")]
+        #[serde(with = "serde_bytes", rename = "data")]
+        pub payload: Vec<u8>,
+        pub id: u32,
+    }
+
+    let old: Packet = serde_json::from_str(r#"{"data":"hi","id":1}"#).unwrap();
+    assert_eq!(old.payload, b"hi");
+    let current: Packet = serde_json::from_str(r#"{"data":[1,2],"id":2}"#).unwrap();
+    assert_eq!(current.payload, [1, 2]);
+    assert_eq!(
+        serde_json::to_string(&current).unwrap(),
+        r#"{"data":[1,2],"id":2}"#
+    );
+
+    // MessagePack tells bytes from sequences, `0xc4` is bin8 where a plain `Vec<u8>` would be
+    // the array `0x92`
+    let bytes = rmp_serde::to_vec_named(&current).unwrap();
+    let data = [0xa4, b'd', b'a', b't', b'a'];
+    let start = bytes.windows(5).position(|w| w == data).unwrap() + data.len();
+    assert_eq!(bytes[start..start + 4], [0xc4, 2, 1, 2]);
+    let read: Packet = rmp_serde::from_slice(&bytes).unwrap();
+    assert_eq!(read, current);
+
+    #[derive(Serialize)]
+    pub struct OldPacket {
+        pub data: String,
+        pub id: u32,
+    }
+
+    let old = OldPacket {
+        data: "hi".into(),
+        id: 3,
+    };
+    let read: Packet = rmp_serde::from_slice(&rmp_serde::to_vec_named(&old).unwrap()).unwrap();
+    assert_eq!(read.payload, b"hi");
+    assert_eq!(read.id, 3);
+}
+
+#[test]
+pub fn test_compatible_fields_default() {
+    use compatible_with::CompatibleFields;
+    use serde::*;
+
+    /// Retries used to be a string, and the earliest releases didn't write them at all
+    #[derive(Deserialize)]
+    pub struct OldRetries(String);
+
+    impl From<OldRetries> for u32 {
+        fn from(old: OldRetries) -> Self {
+            old.0.parse().unwrap_or_default()
+        }
+    }
+
+    fn default_retries() -> u32 {
+        3
+    }
+
+    #[derive(Debug, PartialEq, CompatibleFields)]
+    pub struct Job {
+        pub name: String,
+        #[compatible(old = "OldRetries")]
+        #[serde(default = "default_retries")]
+        pub retries: u32,
+        #[compatible(old = "OldRetries")]
+        #[serde(default, rename = "max_backoff")]
+        pub backoff: u32,
+        /// Tokens used to be text
+        #[compatible(old = "String")]
+        #[serde(with = "serde_bytes", default)]
+        pub token: Vec<u8>,
+    }
+
+    let job = |retries, backoff| Job {
+        name: "a".into(),
+        retries,
+        backoff,
+        token: Vec::new(),
+    };
+    let parse = |input| serde_json::from_str::<Job>(input).unwrap();
+    assert_eq!(parse(r#"{"name":"a"}"#), job(3, 0));
+    assert_eq!(parse(r#"{"name":"a","retries":"5"}"#), job(5, 0));
+    assert_eq!(
+        parse(r#"{"name":"a","retries":7,"max_backoff":"2"}"#),
+        job(7, 2)
+    );
+    assert_eq!(parse(r#"{"name":"a","token":"hi"}"#).token, b"hi");
+    assert_eq!(parse(r#"{"name":"a","token":[1,2]}"#).token, [1, 2]);
+    assert_eq!(
+        serde_json::to_string(&job(1, 2)).unwrap(),
+        r#"{"name":"a","retries":1,"max_backoff":2,"token":[]}"#
+    );
+}
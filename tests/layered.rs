@@ -0,0 +1,84 @@
+use compatible_with::Compatible;
+use config::{Config, Environment, File, FileFormat};
+use serde::*;
+use std::collections::HashMap;
+
+// `config` merges every source into one tree and deserializes it once, so `Compatible` fields
+// migrate per field no matter which layer the old value came from
+
+#[test]
+pub fn test_layered_config() {
+    /// The log level used to be a number
+    #[derive(Deserialize)]
+    pub struct OldLevel(u8);
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Level {
+        Error,
+        Info,
+        Debug,
+    }
+
+    impl From<OldLevel> for Level {
+        fn from(old: OldLevel) -> Self {
+            match old.0 {
+                0 | 1 => Level::Error,
+                2 | 3 => Level::Info,
+                _ => Level::Debug,
+            }
+        }
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    pub struct Settings {
+        #[serde(deserialize_with = "Compatible::<OldLevel, Level>::deserialize_with")]
+        pub level: Level,
+        #[serde(deserialize_with = "Compatible::<u16, u16>::deserialize_from_str")]
+        pub port: u16,
+        pub timeout: Compatible<u32, u64>,
+    }
+
+    let env = HashMap::from([("APP_PORT".to_string(), "8080".to_string())]);
+    let config = Config::builder()
+        .set_default("level", "error")
+        .unwrap()
+        .set_default("port", "80")
+        .unwrap()
+        .add_source(File::from_str(
+            r#"{ "level": 3, "timeout": 30 }"#,
+            FileFormat::Json,
+        ))
+        .add_source(Environment::with_prefix("APP").source(Some(env)))
+        .build()
+        .unwrap();
+
+    let settings: Settings = config.try_deserialize().unwrap();
+    assert_eq!(settings.level, Level::Info);
+    assert_eq!(settings.port, 8080);
+    assert_eq!(settings.timeout.into_current(), 30);
+
+    // Environment values stay strings unless `try_parsing` is on, the untagged match on a
+    // number field can't take them, `deserialize_from_str` fields read them either way
+    let layered = |try_parsing| {
+        let env = HashMap::from([
+            ("APP_PORT".to_string(), "8080".to_string()),
+            ("APP_TIMEOUT".to_string(), "45".to_string()),
+        ]);
+        Config::builder()
+            .set_default("level", 2)
+            .unwrap()
+            .add_source(
+                Environment::with_prefix("APP")
+                    .try_parsing(try_parsing)
+                    .source(Some(env)),
+            )
+            .build()
+            .unwrap()
+            .try_deserialize::<Settings>()
+    };
+    assert!(layered(false).is_err());
+    let settings = layered(true).unwrap();
+    assert_eq!(settings.port, 8080);
+    assert_eq!(settings.timeout.into_current(), 45);
+}
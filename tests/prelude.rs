@@ -0,0 +1,37 @@
+use compatible_with::prelude::*;
+#[test]
+pub fn test_prelude() {
+    use serde::*;
+
+    #[derive(Debug, Deserialize, PartialEq, CompatibleWith)]
+    #[serde(from = "Compatible::<i32, MyType>")]
+    pub struct MyType(String);
+
+    impl From<i32> for MyType {
+        fn from(value: i32) -> Self {
+            MyType(value.to_string())
+        }
+    }
+
+    compatible_default!(default_my_type, i32, MyType);
+
+    #[derive(Deserialize)]
+    pub struct New {
+        pub a: MyType,
+        #[serde(default = "default_my_type")]
+        pub b: MyType,
+    }
+
+    let migrated: New = serde_json::from_str(r#"{"a":1}"#).unwrap();
+    assert_eq!(migrated.a, MyType("1".into()));
+    assert_eq!(migrated.b, MyType("0".into()));
+
+    let value: Compatible<i32, MyType> = Compatible::from_old(2);
+    assert_eq!(value.version(), Version::Old);
+    assert_eq!(value.into_current(), MyType("2".into()));
+
+    let strict = StrictCompatible::new(Compatible::<i32, MyType>::from_old(3));
+    assert_eq!(strict.into_current(), MyType("3".into()));
+    let lazy = Compatible::<i32, MyType>::from_old(4).into_lazy();
+    assert_eq!(lazy.get(), &MyType("4".into()));
+}
@@ -0,0 +1,92 @@
+use compatible_with::Compatible;
+use proptest::prelude::*;
+use serde::*;
+use std::fmt::Debug;
+
+/// Checks that converting, normalizing and serializing an old value terminate and agree with
+/// the plain `From` conversion, and that a current value survives a round-trip untouched
+pub fn check<Old, Current>(old: Old, current: Current) -> Result<(), TestCaseError>
+where
+    Old: Serialize + for<'de> Deserialize<'de> + Clone + Debug,
+    Current: Serialize + for<'de> Deserialize<'de> + From<Old> + Clone + PartialEq + Debug,
+{
+    let expected = Current::from(old.clone());
+
+    let value = Compatible::<Old, Current>::from_old(old.clone());
+    prop_assert_eq!(
+        value.clone().into_current(),
+        expected.clone(),
+        "into_current of {:?}",
+        old
+    );
+    prop_assert_eq!(
+        value.clone().make_current().into_current(),
+        expected.clone(),
+        "make_current of {:?}",
+        old
+    );
+
+    let json = serde_json::to_string(&value).unwrap();
+    let parsed: Compatible<Old, Current> = serde_json::from_str(&json).unwrap();
+    prop_assert_eq!(parsed.into_current(), expected, "round-trip of {}", json);
+
+    let json =
+        serde_json::to_string(&Compatible::<Old, Current>::from_current(current.clone())).unwrap();
+    let parsed: Compatible<Old, Current> = serde_json::from_str(&json).unwrap();
+    prop_assert_eq!(parsed.into_current(), current, "round-trip of {}", json);
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct OldRecord {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Record {
+    pub id: u64,
+    pub name: String,
+    pub aliases: Vec<String>,
+}
+
+impl From<OldRecord> for Record {
+    fn from(old: OldRecord) -> Self {
+        Record {
+            id: old.id.into(),
+            name: old.name,
+            aliases: vec![],
+        }
+    }
+}
+
+pub fn old_record() -> impl Strategy<Value = OldRecord> {
+    (any::<u32>(), any::<String>()).prop_map(|(id, name)| OldRecord { id, name })
+}
+
+pub fn record() -> impl Strategy<Value = Record> {
+    (
+        any::<u64>(),
+        any::<String>(),
+        prop::collection::vec(any::<String>(), 0..4),
+    )
+        .prop_map(|(id, name, aliases)| Record { id, name, aliases })
+}
+
+proptest! {
+    #[test]
+    fn test_widening_integer(old: i32, current: i64) {
+        check::<i32, i64>(old, current)?;
+    }
+
+    #[test]
+    fn test_byte_to_char(old: u8, current: char) {
+        check::<u8, char>(old, current)?;
+    }
+
+    #[test]
+    fn test_struct_migration(old in old_record(), current in record()) {
+        check::<OldRecord, Record>(old, current)?;
+    }
+}